@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// Where pages load their runtime assets (scripts and stylesheets) from.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetMode {
+    /// Third-party assets such as Prism are loaded from a CDN.
+    #[default]
+    Cdn,
+    /// Every asset is written to the output directory, so the site works without
+    /// internet access. Code is always highlighted at compile time.
+    Offline,
+}
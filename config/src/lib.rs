@@ -3,20 +3,56 @@ use std::fs;
 use std::io::Write;
 use std::path::Path;
 
+mod assets;
+mod markdown;
 mod owner;
+mod run;
+mod security;
+mod social;
 mod styles;
 
+pub use assets::AssetMode;
+pub use markdown::{CitationStyle, FootnoteStyle, MarkdownConfig, RawHtml};
 use owner::BlogOwner;
+use run::RunConfig;
+pub use security::SecurityConfig;
 use serde::{Deserialize, Serialize};
-use styles::BlogStyles;
+pub use social::SocialConfig;
+pub use styles::HighlightEngine;
+use styles::{BlogStyles, HighlightConfig};
 
 #[derive(Serialize, Deserialize)]
 pub struct MejiroConfig {
     pub owner: BlogOwner,
     pub site_title: String,
+    /// Public URL of the site, e.g. `https://example.com/blog`. Needed for absolute
+    /// links in social media previews.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_url: Option<String>,
     pub styles: BlogStyles,
     #[serde(default = "default_images_dir")]
     pub images_dir: String,
+    /// Extra directories (besides the posts directory) that `@code` includes may read from.
+    #[serde(default)]
+    pub include_roots: Vec<String>,
+    /// Enables `@run[...]` when present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<RunConfig>,
+    /// Rendering settings, such as how footnotes are presented.
+    #[serde(default)]
+    pub markdown: MarkdownConfig,
+    /// Site-wide BibTeX file for `@cite[...]` citations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bibliography: Option<String>,
+    /// `offline` writes every runtime asset to the output directory instead of using CDNs.
+    #[serde(default)]
+    pub assets: AssetMode,
+    /// Subresource Integrity and Content Security Policy settings.
+    #[serde(default)]
+    pub security: SecurityConfig,
+    /// Social media preview settings.
+    #[serde(default)]
+    pub social: SocialConfig,
 }
 
 fn default_images_dir() -> String {
@@ -48,11 +84,20 @@ impl MejiroConfig {
                 linkedin_link: "https://linkedin.com/in/your-profile".to_string(),
             },
             site_title: "My Blog".to_string(),
+            site_url: None,
             styles: BlogStyles {
                 css_file: "style.css".to_string(),
                 icon: "icon.png".to_string(),
+                highlight: HighlightConfig::default(),
             },
             images_dir: "images".to_string(),
+            include_roots: vec![],
+            run: None,
+            markdown: MarkdownConfig::default(),
+            bibliography: None,
+            assets: AssetMode::default(),
+            security: SecurityConfig::default(),
+            social: SocialConfig::default(),
         };
 
         Self::write_config_file(config_path, &default_config);
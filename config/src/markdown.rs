@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+/// How footnotes are presented on post pages.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum FootnoteStyle {
+    /// Numbered notes at the end of the article, previewed on hover.
+    #[default]
+    End,
+    /// Notes in the margin next to their reference on wide screens, falling back to
+    /// end-of-article notes on narrow screens.
+    Sidenotes,
+}
+
+/// How `@cite[...]` citations and the References section are rendered.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum CitationStyle {
+    /// `[1]`, with references numbered in order of first citation.
+    #[default]
+    Numeric,
+    /// `(Knuth 1984)`, with references sorted by author.
+    AuthorYear,
+}
+
+/// What happens to HTML written directly in post markdown.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum RawHtml {
+    /// Passed through untouched.
+    #[default]
+    Allow,
+    /// Reduced to a safe allowlist of tags and attributes.
+    Sanitize,
+    /// Escaped, so it shows as text.
+    Deny,
+}
+
+/// Settings for how post markdown is rendered.
+#[derive(Serialize, Deserialize, Default)]
+pub struct MarkdownConfig {
+    #[serde(default)]
+    pub footnotes: FootnoteStyle,
+    #[serde(default)]
+    pub citations: CitationStyle,
+    #[serde(default)]
+    pub raw_html: RawHtml,
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Settings for the opt-in `@run[...]` directive, which executes scripts at build time.
+#[derive(Serialize, Deserialize)]
+pub struct RunConfig {
+    /// Maps a file extension to the command used to run it, e.g. `py: python3`.
+    pub interpreters: BTreeMap<String, String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
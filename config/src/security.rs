@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// Hardening of the generated pages.
+#[derive(Serialize, Deserialize)]
+pub struct SecurityConfig {
+    /// Adds Subresource Integrity hashes to external scripts and stylesheets.
+    #[serde(default = "default_sri")]
+    pub sri: bool,
+    /// Adds a Content Security Policy `<meta>` tag computed from what each page loads.
+    #[serde(default)]
+    pub csp: bool,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            sri: default_sri(),
+            csp: false,
+        }
+    }
+}
+
+fn default_sri() -> bool {
+    true
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// How pages look when shared on social media.
+#[derive(Serialize, Deserialize, Default)]
+pub struct SocialConfig {
+    /// Image for pages without their own, relative to the output directory or a full URL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// Font file for generated card images. Cards are generated when it is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub card_font: Option<String>,
+}
@@ -5,6 +5,8 @@ pub struct BlogStyles {
     // theme: String,
     pub icon: String,
     pub css_file: String,
+    #[serde(default)]
+    pub highlight: HighlightConfig,
 }
 
 impl BlogStyles {
@@ -13,6 +15,68 @@ impl BlogStyles {
             // theme: theme.to_string(),
             icon: icon.to_string(),
             css_file: css_file.to_string(),
+            highlight: HighlightConfig::default(),
         }
     }
 }
+
+/// Where fenced code blocks are syntax highlighted.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightEngine {
+    /// In the browser, with Prism loaded from a CDN.
+    #[default]
+    Prism,
+    /// At compile time, into static HTML.
+    Static,
+}
+
+/// Syntax highlighting settings.
+///
+/// Each engine has a theme for the light and one for the dark colour scheme; the page
+/// switches between them along with its own colours.
+#[derive(Serialize, Deserialize)]
+pub struct HighlightConfig {
+    #[serde(default)]
+    pub engine: HighlightEngine,
+    /// Light theme for the `static` engine, e.g. `InspiredGitHub` or `base16-ocean.light`.
+    #[serde(default = "default_theme")]
+    pub theme: String,
+    /// Dark theme for the `static` engine, e.g. `base16-ocean.dark`.
+    #[serde(default = "default_dark_theme")]
+    pub dark_theme: String,
+    /// Light theme for the `prism` engine, e.g. `prism` or `prism-solarizedlight`.
+    #[serde(default = "default_prism_theme")]
+    pub prism_theme: String,
+    /// Dark theme for the `prism` engine, e.g. `prism-tomorrow` or `prism-okaidia`.
+    #[serde(default = "default_prism_dark_theme")]
+    pub prism_dark_theme: String,
+}
+
+impl Default for HighlightConfig {
+    fn default() -> Self {
+        Self {
+            engine: HighlightEngine::default(),
+            theme: default_theme(),
+            dark_theme: default_dark_theme(),
+            prism_theme: default_prism_theme(),
+            prism_dark_theme: default_prism_dark_theme(),
+        }
+    }
+}
+
+fn default_theme() -> String {
+    "InspiredGitHub".to_string()
+}
+
+fn default_dark_theme() -> String {
+    "base16-ocean.dark".to_string()
+}
+
+fn default_prism_theme() -> String {
+    "prism".to_string()
+}
+
+fn default_prism_dark_theme() -> String {
+    "prism-tomorrow".to_string()
+}
@@ -1,8 +1,13 @@
+use crate::color_scheme::color_scheme_toggle_html;
+use crate::escape::escape_html;
+use crate::svg_icon::Icon;
+
 pub fn aside_html(
     owner_name: &str,
     owner_github_link: &str,
     owner_linkedin_link: &str,
     icon_path: &str,
+    home_path: &str,
 ) -> String {
     format!(
         r#"
@@ -12,11 +17,20 @@ pub fn aside_html(
     <span>{owner_name}</span>
   </div>
   <nav class="links">
-    <a href="/">Home</a>
-    <a href="{owner_github_link}">GitHub</a>
-    <a href="{owner_linkedin_link}">LinkedIn</a>
+    <a href="{home_path}">Home</a>
+    <a href="{owner_github_link}">{github_icon} GitHub</a>
+    <a href="{owner_linkedin_link}">{linkedin_icon} LinkedIn</a>
   </nav>
+  {toggle}
 </aside>
-"#
+"#,
+        icon_path = escape_html(icon_path),
+        owner_name = escape_html(owner_name),
+        home_path = escape_html(home_path),
+        owner_github_link = escape_html(owner_github_link),
+        owner_linkedin_link = escape_html(owner_linkedin_link),
+        github_icon = Icon::GitHub.svg(),
+        linkedin_icon = Icon::LinkedIn.svg(),
+        toggle = color_scheme_toggle_html(),
     )
 }
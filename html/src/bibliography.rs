@@ -0,0 +1,512 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::directive::expand_directives;
+use crate::escape::escape_html;
+use crate::options::CitationStyle;
+use crate::raw_html::{generated, is_safe_url};
+
+/// A BibTeX entry such as `@article{key, author = {...}, ...}`.
+#[derive(Debug, PartialEq)]
+pub struct Entry {
+    /// Entry type in lowercase, e.g. `article` or `book`.
+    pub kind: String,
+    /// Field values with braces removed, keyed by lowercase field name.
+    pub fields: BTreeMap<String, String>,
+}
+
+/// Splits `text`, which starts with an opening delimiter, at the matching `close`,
+/// returning the content in between and the rest after `close`. Braces nest.
+fn split_delimited(text: &str, close: char) -> Option<(&str, &str)> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices().skip(1) {
+        if depth == 0 && c == close {
+            return Some((&text[1..i], &text[i + 1..]));
+        }
+        match c {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// TeX logos, whose control words are replaced with their name.
+const TEX_LOGOS: &[&str] = &["TeX", "LaTeX", "LaTeXe", "BibTeX", "XeTeX", "LuaTeX"];
+
+/// The combining character for a TeX accent command such as `\'` (acute) or `\c`
+/// (cedilla).
+fn combining_accent(command: &str) -> Option<char> {
+    Some(match command {
+        "'" => '\u{301}',
+        "`" => '\u{300}',
+        "^" => '\u{302}',
+        "\"" => '\u{308}',
+        "~" => '\u{303}',
+        "=" => '\u{304}',
+        "." => '\u{307}',
+        "H" => '\u{30B}',
+        "c" => '\u{327}',
+        "u" => '\u{306}',
+        "v" => '\u{30C}',
+        _ => return None,
+    })
+}
+
+/// Replaces TeX control sequences in `text`: escaped characters such as `\&` become the
+/// character, accents such as `\"{o}` a letter with a combining accent, logos such as
+/// `\TeX` their name, and other commands such as `\emph` are dropped (their argument
+/// is kept). Braces are left in place, as they end control words.
+fn strip_control_sequences(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(if c == '~' { ' ' } else { c });
+            continue;
+        }
+        let Some(first) = chars.next() else {
+            break;
+        };
+        let mut command = String::from(first);
+        if first.is_ascii_alphabetic() {
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_alphabetic()) {
+                command.push(c);
+                chars.next();
+            }
+            while chars.peek() == Some(&' ') {
+                chars.next();
+            }
+        }
+        if let Some(accent) = combining_accent(&command) {
+            while chars.peek().is_some_and(|&c| c == ' ' || c == '{') {
+                chars.next();
+            }
+            if let Some(letter) = chars.next() {
+                output.push(letter);
+                output.push(accent);
+            }
+        } else if TEX_LOGOS.contains(&command.as_str()) {
+            output.push_str(&command);
+        } else if first == '\\' {
+            output.push(' ');
+        } else if !first.is_ascii_alphabetic() {
+            output.push(first);
+        }
+    }
+    output
+}
+
+/// Turns a raw BibTeX value into plain text: TeX control sequences are replaced, braces
+/// dropped and whitespace collapsed.
+fn clean_value(value: &str) -> String {
+    strip_control_sequences(value)
+        .replace(['{', '}'], "")
+        .replace("---", "—")
+        .replace("--", "–")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses the `name = value, ...` list of an entry. Values may be `{braced}`, `"quoted"`
+/// or bare, and joined with `#`. Bare names are replaced with their value in `strings`.
+fn parse_fields(
+    mut text: &str,
+    strings: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, String> {
+    let mut fields = BTreeMap::new();
+    loop {
+        text = text.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if text.is_empty() {
+            return Ok(fields);
+        }
+
+        let (name, rest) = text
+            .split_once('=')
+            .ok_or_else(|| format!("expected `=` after `{}`", text.trim()))?;
+        let name = name.trim().to_ascii_lowercase();
+        text = rest;
+
+        let mut value = String::new();
+        loop {
+            text = text.trim_start();
+            let (part, rest) = match text.chars().next() {
+                Some('{') => split_delimited(text, '}')
+                    .ok_or_else(|| format!("unclosed `{{` in field `{name}`"))?,
+                Some('"') => {
+                    let end = text[1..]
+                        .find('"')
+                        .ok_or_else(|| format!("unclosed `\"` in field `{name}`"))?;
+                    (&text[1..end + 1], &text[end + 2..])
+                }
+                _ => {
+                    let end = text.find([',', '#']).unwrap_or(text.len());
+                    let part = text[..end].trim();
+                    let part = strings
+                        .get(&part.to_ascii_lowercase())
+                        .map_or(part, String::as_str);
+                    (part, &text[end..])
+                }
+            };
+            value.push_str(part);
+            text = rest.trim_start();
+            match text.strip_prefix('#') {
+                Some(rest) => text = rest,
+                None => break,
+            }
+        }
+        fields.insert(name, clean_value(&value));
+    }
+}
+
+/// Parses the entries of a BibTeX file by citation key.
+///
+/// `@string` macros are expanded in the entries after them, and `@comment` and
+/// `@preamble` blocks are skipped.
+pub fn parse_bibtex(source: &str) -> Result<BTreeMap<String, Entry>, String> {
+    let mut entries = BTreeMap::new();
+    let mut strings = BTreeMap::new();
+    let mut rest = source;
+
+    while let Some(at) = rest.find('@') {
+        rest = &rest[at + 1..];
+        let kind_len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        let kind = rest[..kind_len].to_ascii_lowercase();
+        let body = rest[kind_len..].trim_start();
+        let close = match body.chars().next() {
+            Some('{') => '}',
+            Some('(') => ')',
+            // A stray `@` outside of an entry
+            _ => continue,
+        };
+        let (body, after) =
+            split_delimited(body, close).ok_or_else(|| format!("unclosed `@{kind}` entry"))?;
+        rest = after;
+
+        if kind == "string" {
+            let macros = parse_fields(body, &strings).map_err(|e| format!("`@string`: {e}"))?;
+            strings.extend(macros);
+            continue;
+        }
+        if matches!(kind.as_str(), "comment" | "preamble") {
+            continue;
+        }
+
+        let (key, fields) = body.split_once(',').unwrap_or((body, ""));
+        let key = key.trim();
+        if key.is_empty() {
+            return Err(format!("`@{kind}` entry without a citation key"));
+        }
+        let fields = parse_fields(fields, &strings).map_err(|e| format!("entry `{key}`: {e}"))?;
+        entries.insert(key.to_string(), Entry { kind, fields });
+    }
+
+    Ok(entries)
+}
+
+/// Reads and parses a BibTeX file.
+pub fn load_bibliography(path: &Path) -> Result<BTreeMap<String, Entry>, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    parse_bibtex(&source).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+/// Returns the last name from `Last, First` or `First Last`.
+fn last_name(name: &str) -> &str {
+    match name.split_once(',') {
+        Some((last, _)) => last.trim(),
+        None => name.split_whitespace().last().unwrap_or(name),
+    }
+}
+
+fn names(entry: &Entry) -> Vec<&str> {
+    entry
+        .fields
+        .get("author")
+        .or_else(|| entry.fields.get("editor"))
+        .map(|names| names.split(" and ").map(str::trim).collect())
+        .unwrap_or_default()
+}
+
+/// The author part of an author-year citation, e.g. `Knuth`, `Knuth and Lamport` or `Knuth et al.`.
+fn author_label(entry: &Entry, key: &str) -> String {
+    match names(entry).as_slice() {
+        [] => key.to_string(),
+        [one] => last_name(one).to_string(),
+        [first, second] => format!("{} and {}", last_name(first), last_name(second)),
+        [first, ..] => format!("{} et al.", last_name(first)),
+    }
+}
+
+fn year(entry: &Entry) -> &str {
+    entry.fields.get("year").map_or("n.d.", String::as_str)
+}
+
+/// Formats an entry for the References section. A `url` with a scheme other than `http`,
+/// `https` or `mailto` is left out.
+fn entry_html(entry: &Entry) -> String {
+    let field = |name: &str| entry.fields.get(name).map(|value| escape_html(value));
+    let mut parts = Vec::new();
+
+    let names = names(entry);
+    if let Some((last, rest)) = names.split_last() {
+        let authors = if rest.is_empty() {
+            last.to_string()
+        } else {
+            format!("{} and {last}", rest.join(", "))
+        };
+        parts.push(escape_html(&authors));
+    }
+    parts.push(escape_html(year(entry)));
+
+    if let Some(title) = field("title") {
+        match entry.kind.as_str() {
+            "book" | "phdthesis" | "mastersthesis" => parts.push(format!("<em>{title}</em>")),
+            _ => parts.push(title),
+        }
+    }
+
+    let mut venue = match (field("journal"), field("booktitle")) {
+        (Some(journal), _) => format!("<em>{journal}</em>"),
+        (None, Some(booktitle)) => format!("In <em>{booktitle}</em>"),
+        (None, None) => String::new(),
+    };
+    if let Some(volume) = field("volume") {
+        venue.push_str(&format!(", {volume}"));
+        if let Some(number) = field("number") {
+            venue.push_str(&format!("({number})"));
+        }
+    }
+    if let Some(pages) = field("pages") {
+        venue.push_str(&format!(", {pages}"));
+    }
+    if !venue.is_empty() {
+        parts.push(venue.trim_start_matches(", ").to_string());
+    }
+    if let Some(publisher) = field("publisher")
+        .or_else(|| field("institution"))
+        .or_else(|| field("school"))
+    {
+        parts.push(publisher);
+    }
+
+    let mut html = format!("{}.", parts.join(". "));
+    let link = field("doi")
+        .map(|doi| format!("https://doi.org/{doi}"))
+        .or_else(|| field("url").filter(|url| is_safe_url(url)));
+    if let Some(link) = link {
+        html.push_str(&format!(" <a href=\"{link}\">{link}</a>"));
+    }
+    html
+}
+
+/// Replaces `@cite[key]` and `@cite[key1, key2]` (`;` also separates keys) with in-text citations and appends a
+/// References section listing the cited entries.
+///
+/// With [`CitationStyle::Numeric`] entries are numbered in order of first citation
+/// (`[1, 2]`); with [`CitationStyle::AuthorYear`] citations read `(Knuth 1984; Lamport 1994)`
+/// and the references are sorted by author. Citations inside code are left alone.
+/// Returns the markdown and the cited keys that are not in `bibliography`.
+pub fn render_citations(
+    markdown: &str,
+    bibliography: &BTreeMap<String, Entry>,
+    style: CitationStyle,
+) -> (String, Vec<String>) {
+    let mut cited: Vec<&str> = Vec::new();
+    let mut unknown: Vec<String> = Vec::new();
+
+    let mut markdown = expand_directives(markdown, |name, arg, _| {
+        if name != "cite" {
+            return None;
+        }
+
+        let mut links = Vec::new();
+        for key in arg
+            .split([',', ';'])
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+        {
+            let Some((key, entry)) = bibliography.get_key_value(key) else {
+                if !unknown.iter().any(|k| k == key) {
+                    unknown.push(key.to_string());
+                }
+                continue;
+            };
+            let number = match cited.iter().position(|k| k == key) {
+                Some(index) => index + 1,
+                None => {
+                    cited.push(key);
+                    cited.len()
+                }
+            };
+            let label = match style {
+                CitationStyle::Numeric => number.to_string(),
+                CitationStyle::AuthorYear => {
+                    format!("{} {}", author_label(entry, key), year(entry))
+                }
+            };
+            links.push(format!(
+                "<a href=\"#ref-{id}\">{label}</a>",
+                id = escape_html(key),
+                label = escape_html(&label)
+            ));
+        }
+
+        if links.is_empty() {
+            return None;
+        }
+        Some(generated(&match style {
+            CitationStyle::Numeric => {
+                format!("<span class=\"citation\">[{}]</span>", links.join(", "))
+            }
+            CitationStyle::AuthorYear => {
+                format!("<span class=\"citation\">({})</span>", links.join("; "))
+            }
+        }))
+    });
+
+    if !cited.is_empty() {
+        if style == CitationStyle::AuthorYear {
+            cited.sort_by_key(|key| {
+                let entry = &bibliography[*key];
+                (author_label(entry, key), year(entry).to_string())
+            });
+        }
+        let list = match style {
+            CitationStyle::Numeric => "ol",
+            CitationStyle::AuthorYear => "ul",
+        };
+        let items: Vec<String> = cited
+            .iter()
+            .map(|key| {
+                format!(
+                    "<li id=\"ref-{id}\">{entry}</li>",
+                    id = escape_html(key),
+                    entry = entry_html(&bibliography[*key])
+                )
+            })
+            .collect();
+        markdown.push_str(&generated(&format!(
+            "\n\n<section class=\"references\">\n<h2>References</h2>\n<{list}>\n{}\n</{list}>\n</section>\n",
+            items.join("\n")
+        )));
+    }
+
+    (markdown, unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_html::without_markers;
+
+    const BIB: &str = r#"
+% Comments and stray @ signs are ignored
+@string{acm = "ACM"}
+
+@book{knuth1984,
+  author = {Donald E. Knuth},
+  title = {The {\TeX}book},
+  publisher = "Addison-Wesley",
+  year = 1984,
+}
+
+@article{lamport1978,
+  author = {Lamport, Leslie},
+  title = {Time, Clocks, and the Ordering of Events in a Distributed System},
+  journal = {Communications of the } # acm,
+  volume = {21},
+  number = {7},
+  pages = {558--565},
+  year = {1978},
+  doi = {10.1145/359545.359563}
+}
+
+@inproceedings(dean2004, author = {Jeffrey Dean and Sanjay Ghemawat and Someone Else},
+  title = {MapReduce}, booktitle = {OSDI}, year = {2004})
+"#;
+
+    #[test]
+    fn test_parse_bibtex() {
+        let entries = parse_bibtex(BIB).unwrap();
+
+        assert_eq!(entries.len(), 3);
+        let knuth = &entries["knuth1984"];
+        assert_eq!(knuth.kind, "book");
+        assert_eq!(knuth.fields["title"], "The TeXbook");
+        assert_eq!(knuth.fields["year"], "1984");
+        let lamport = &entries["lamport1978"];
+        assert_eq!(lamport.fields["journal"], "Communications of the ACM");
+        assert_eq!(lamport.fields["pages"], "558–565");
+        assert_eq!(entries["dean2004"].fields["booktitle"], "OSDI");
+
+        assert!(parse_bibtex("@book{x, title = {unclosed}").is_err());
+    }
+
+    #[test]
+    fn test_clean_value() {
+        assert_eq!(
+            clean_value(r#"Erd{\H o}s and G\"{o}del"#),
+            "Erdo\u{30B}s and Go\u{308}del"
+        );
+        assert_eq!(
+            clean_value(r"\emph{Fast} \LaTeX\ macros \& more"),
+            "Fast LaTeX macros & more"
+        );
+        assert_eq!(clean_value("{Caf\\'e}~au---lait"), "Cafe\u{301} au—lait");
+    }
+
+    #[test]
+    fn test_entry_html_skips_unsafe_urls() {
+        let entries = parse_bibtex(
+            "@misc{a, title = {A}, url = {javascript:alert(1)}}\n\
+             @misc{b, title = {B}, url = {https://example.com/b}}",
+        )
+        .unwrap();
+
+        assert!(!entry_html(&entries["a"]).contains("href"));
+        assert!(
+            entry_html(&entries["b"])
+                .ends_with(" <a href=\"https://example.com/b\">https://example.com/b</a>")
+        );
+    }
+
+    #[test]
+    fn test_render_citations_numeric() {
+        let bibliography = parse_bibtex(BIB).unwrap();
+        let markdown =
+            "See @cite[lamport1978] and @cite[knuth1984, lamport1978, nope].\n\n`@cite[knuth1984]`";
+        let (result, unknown) = render_citations(markdown, &bibliography, CitationStyle::Numeric);
+        let result = without_markers(&result);
+
+        assert!(result.starts_with(
+            "See <span class=\"citation\">[<a href=\"#ref-lamport1978\">1</a>]</span> and <span class=\"citation\">[<a href=\"#ref-knuth1984\">2</a>, <a href=\"#ref-lamport1978\">1</a>]</span>."
+        ));
+        assert!(result.contains("`@cite[knuth1984]`"));
+        assert!(result.contains(
+            "<ol>\n<li id=\"ref-lamport1978\">Lamport, Leslie. 1978. Time, Clocks, and the Ordering of Events in a Distributed System. <em>Communications of the ACM</em>, 21(7), 558–565. <a href=\"https://doi.org/10.1145/359545.359563\">https://doi.org/10.1145/359545.359563</a></li>\n<li id=\"ref-knuth1984\">Donald E. Knuth. 1984. <em>The TeXbook</em>. Addison-Wesley.</li>\n</ol>"
+        ));
+        assert_eq!(unknown, vec!["nope".to_string()]);
+    }
+
+    #[test]
+    fn test_render_citations_author_year() {
+        let bibliography = parse_bibtex(BIB).unwrap();
+        let markdown = "@cite[lamport1978; dean2004] @cite[dean2004, knuth1984]";
+        let (result, _) = render_citations(markdown, &bibliography, CitationStyle::AuthorYear);
+        let result = without_markers(&result);
+
+        assert!(result.contains(
+            "<span class=\"citation\">(<a href=\"#ref-dean2004\">Dean et al. 2004</a>; <a href=\"#ref-knuth1984\">Knuth 1984</a>)</span>"
+        ));
+        // References are sorted by author, not by order of citation
+        let dean = result.find("<li id=\"ref-dean2004\">").unwrap();
+        let knuth = result.find("<li id=\"ref-knuth1984\">").unwrap();
+        assert!(dean < knuth);
+        assert!(result.contains("<ul>"));
+    }
+}
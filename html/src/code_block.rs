@@ -1,8 +1,16 @@
-use std::fs;
 use std::path::Path;
 
+use crate::code_fence::fence_for;
+use crate::diagnostic::Diagnostic;
+use crate::directive::{
+    IncludeContext, IncludePathError, expand_directives, missing_include_html, read_include,
+    resolve_virtual_include_path,
+};
+use crate::git::read_file_at_revision;
+use crate::notebook::{Notebook, NotebookOptions};
+
 /// Infers the language identifier from a file path based on its extension.
-fn infer_language_from_path(path: &str) -> &str {
+pub(crate) fn infer_language_from_path(path: &str) -> &str {
     if let Some(ext) = Path::new(path).extension() {
         match ext.to_str().unwrap_or("") {
             "rs" => "rust",
@@ -38,20 +46,152 @@ fn infer_language_from_path(path: &str) -> &str {
     }
 }
 
+/// Expands a single `@code[filepath]` directive into a fenced markdown code block.
+///
+/// `@code[filepath@revision]` reads the file from the local git repository at that tag,
+/// branch or commit instead of the working tree (see [`read_file_at_revision`]). An
+/// argument that exists as a file is read as-is, so file names may contain `@`;
+/// otherwise a missing repository or an unknown revision is an error.
+///
+/// A missing or unreadable file is reported in `diagnostics` and replaced with a
+/// visible placeholder block instead of the raw directive.
+pub(crate) fn expand_code_include(
+    arg: &str,
+    line: usize,
+    ctx: &IncludeContext,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> String {
+    let (file_path, revision) = split_revision(arg, ctx.base_dir());
+
+    let content = match revision {
+        Some(revision) => read_revision_include(file_path, revision, line, ctx, diagnostics),
+        // Resolve the file path relative to the markdown file's directory
+        None => read_include("code", arg, line, ctx, diagnostics).map(|(_, content)| content),
+    };
+
+    match content {
+        Some(code_content) => {
+            // Infer language from extension
+            let lang = infer_language_from_path(file_path);
+
+            // Create markdown code block, titled with the included path
+            let fence = fence_for(&code_content);
+            format!("{fence}{lang} title=\"{arg}\"\n{code_content}\n{fence}")
+        }
+        // File not found - render a visible placeholder instead of the raw directive
+        None => missing_include_html("code", arg),
+    }
+}
+
+/// Splits `path@revision` into its parts. A path that exists as-is in the working tree is
+/// never split, so file names containing `@` keep working, and neither is one where the
+/// `@` starts a path segment, as in `node_modules/@types/node/index.d.ts`.
+fn split_revision<'a>(arg: &'a str, base_dir: &Path) -> (&'a str, Option<&'a str>) {
+    match arg.rsplit_once('@') {
+        Some((path, revision))
+            if !path.is_empty()
+                && !path.ends_with('/')
+                && !revision.is_empty()
+                && !base_dir.join(arg).exists() =>
+        {
+            (path, Some(revision))
+        }
+        _ => (arg, None),
+    }
+}
+
+/// Reads `file_path` at a git `revision`, reporting failures (including a missing
+/// repository or an unknown revision) as error diagnostics.
+fn read_revision_include(
+    file_path: &str,
+    revision: &str,
+    line: usize,
+    ctx: &IncludeContext,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<String> {
+    let result =
+        match resolve_virtual_include_path(ctx.base_dir(), file_path, &ctx.options.include_roots) {
+            Ok(full_path) => read_file_at_revision(&full_path, revision),
+            Err(IncludePathError::OutsideRoots) => {
+                Err("resolves outside the allowed include directories".to_string())
+            }
+            Err(IncludePathError::NotFound) => Err("directory not found".to_string()),
+        };
+    result
+        .map_err(|e| {
+            diagnostics.push(Diagnostic::error(
+                ctx.path,
+                line,
+                format!("`@code[{file_path}@{revision}]`: {e}"),
+            ));
+        })
+        .ok()
+}
+
+/// Expands a single `@notebook[file.ipynb options...]` directive into rendered markdown.
+///
+/// Supported options are `hide_input`, `hide_output` and `cells=<start>-<end>` (1-based,
+/// inclusive), e.g. `@notebook[analysis.ipynb hide_input cells=2-5]`. See
+/// [`Notebook::to_markdown`] for how cells and outputs are rendered.
+pub(crate) fn expand_notebook_include(
+    arg: &str,
+    line: usize,
+    ctx: &IncludeContext,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> String {
+    let mut parts = arg.split_whitespace();
+    let file_path = parts.next().unwrap_or_default();
+    let options: Vec<&str> = parts.collect();
+
+    let options = match NotebookOptions::parse(&options) {
+        Ok(options) => options,
+        Err(e) => {
+            diagnostics.push(Diagnostic::warning(
+                ctx.path,
+                line,
+                format!("`@notebook[{arg}]`: {e}"),
+            ));
+            NotebookOptions::default()
+        }
+    };
+
+    let Some((_, json)) = read_include("notebook", file_path, line, ctx, diagnostics) else {
+        return missing_include_html("notebook", file_path);
+    };
+
+    match Notebook::from_json(&json) {
+        Ok(notebook) => notebook.to_markdown(&options),
+        Err(e) => {
+            diagnostics.push(Diagnostic::warning(
+                ctx.path,
+                line,
+                format!("`@notebook` could not parse {file_path}: {e}"),
+            ));
+            missing_include_html("notebook", file_path)
+        }
+    }
+}
+
 /// Preprocesses markdown content to replace @code[filepath] directives with actual code blocks.
 ///
 /// Searches for @code[...] patterns and replaces them with markdown code blocks containing
 /// the content of the referenced files. The file paths are resolved relative to the
-/// directory containing the markdown file.
+/// directory containing the markdown file and must stay inside one of the configured
+/// include roots (see [`resolve_include_path`](crate::directive::resolve_include_path)).
+///
+/// A missing or unreadable file is reported as a warning in `diagnostics` and replaced
+/// with a visible placeholder block; a path outside the include roots is reported as an error.
 ///
-/// If a file is not found, the @code[...] directive is left as-is in the output.
+/// Other directives are left untouched; use
+/// [`preprocess_directives`](crate::directive::preprocess_directives) to expand all of them.
 ///
 /// # Arguments
 /// * `markdown` - The markdown content to preprocess
-/// * `base_dir` - The directory containing the markdown file (used to resolve relative paths)
+/// * `ctx` - The markdown file being processed (used to resolve relative paths and report lines)
+/// * `diagnostics` - Collects warnings and errors found while expanding the directives
 ///
 /// # Returns
-/// The preprocessed markdown with @code[...] directives replaced by code blocks (or left as-is if files don't exist)
+/// The preprocessed markdown with @code[...] directives replaced by code blocks
 ///
 /// # Example
 /// ```ignore
@@ -61,57 +201,26 @@ fn infer_language_from_path(path: &str) -> &str {
 /// // <content of main.py>
 /// // ```
 /// ```
-pub fn preprocess_code_includes(markdown: &str, base_dir: &Path) -> String {
-    let mut result = String::new();
-    let mut last_pos = 0;
-
-    while let Some(start) = markdown[last_pos..].find("@code[") {
-        let start_pos = last_pos + start;
-
-        // Append everything before @code[
-        result.push_str(&markdown[last_pos..start_pos]);
-
-        // Find the closing ]
-        if let Some(end) = markdown[start_pos + 6..].find(']') {
-            let end_pos = start_pos + 6 + end;
-            let file_path = &markdown[start_pos + 6..end_pos];
-
-            // Resolve the file path relative to the markdown file's directory
-            let full_path = base_dir.join(file_path);
-
-            // Try to read the file content
-            match fs::read_to_string(&full_path) {
-                Ok(code_content) => {
-                    // Infer language from extension
-                    let lang = infer_language_from_path(file_path);
-
-                    // Create markdown code block
-                    let code_block = format!("```{}\n{}\n```", lang, code_content);
-                    result.push_str(&code_block);
-                }
-                Err(_) => {
-                    // File not found - leave the @code[...] directive as-is
-                    result.push_str(&markdown[start_pos..end_pos + 1]);
-                }
-            }
-
-            last_pos = end_pos + 1;
-        } else {
-            // No closing bracket found, just append the @code[ and continue
-            result.push_str("@code[");
-            last_pos = start_pos + 6;
-        }
-    }
-
-    // Append the remaining content
-    result.push_str(&markdown[last_pos..]);
-
-    result
+pub fn preprocess_code_includes(
+    markdown: &str,
+    ctx: &IncludeContext,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> String {
+    expand_directives(markdown, |name, arg, pos| {
+        (name == "code").then(|| {
+            let line = ctx.line_of(markdown, pos);
+            expand_code_include(arg, line, ctx, diagnostics)
+        })
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::diagnostic::Severity;
+    use crate::options::RenderOptions;
+    use crate::raw_html::without_markers;
+    use crate::test_fixtures::preprocess_with;
     use std::fs;
     use std::io::Write;
     use tempfile::TempDir;
@@ -136,30 +245,192 @@ mod tests {
         writeln!(file, "    print('Hello, World!')").unwrap();
 
         let markdown = "# Test\n\n@code[test.py]\n\nSome text";
-        let result = preprocess_code_includes(markdown, temp_dir.path());
+        let (result, diagnostics) = preprocess_with(
+            preprocess_code_includes,
+            markdown,
+            temp_dir.path(),
+            &Default::default(),
+        );
 
-        assert!(result.contains("```python"));
+        assert!(result.contains("```python title=\"test.py\""));
         assert!(result.contains("def hello():"));
         assert!(result.contains("print('Hello, World!')"));
         assert!(result.contains("Some text"));
+        assert!(diagnostics.is_empty());
     }
 
     #[test]
     fn test_preprocess_code_includes_missing_file() {
         let temp_dir = TempDir::new().unwrap();
-        let markdown = "@code[nonexistent.py]";
-        let result = preprocess_code_includes(markdown, temp_dir.path());
+        let markdown = "# Test\n\n@code[nonexistent.py]";
+        let (result, diagnostics) = preprocess_with(
+            preprocess_code_includes,
+            markdown,
+            temp_dir.path(),
+            &Default::default(),
+        );
+        let result = without_markers(&result);
+
+        // When file doesn't exist, a visible placeholder replaces the directive
+        assert!(!result.contains("@code[nonexistent.py]"));
+        assert!(result.contains(r#"<div class="include-missing">"#));
+        assert!(result.contains("<code>nonexistent.py</code>"));
 
-        // When file doesn't exist, the directive should be left as-is
-        assert_eq!(result, "@code[nonexistent.py]");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].line, 3);
+        assert!(diagnostics[0].message.contains("nonexistent.py"));
     }
 
     #[test]
     fn test_preprocess_no_code_includes() {
         let temp_dir = TempDir::new().unwrap();
         let markdown = "# Test\n\nJust regular markdown content.";
-        let result = preprocess_code_includes(markdown, temp_dir.path());
+        let (result, diagnostics) = preprocess_with(
+            preprocess_code_includes,
+            markdown,
+            temp_dir.path(),
+            &Default::default(),
+        );
 
         assert_eq!(result, markdown);
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_preprocess_code_includes_rejects_parent_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let posts_dir = temp_dir.path().join("posts");
+        fs::create_dir(&posts_dir).unwrap();
+        fs::write(temp_dir.path().join("secret.txt"), "top secret").unwrap();
+
+        let markdown = "@code[../secret.txt]";
+        let (result, diagnostics) = preprocess_with(
+            preprocess_code_includes,
+            markdown,
+            &posts_dir,
+            &Default::default(),
+        );
+
+        assert!(!result.contains("top secret"));
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+    }
+
+    #[test]
+    fn test_preprocess_code_includes_rejects_absolute_path() {
+        let temp_dir = TempDir::new().unwrap();
+        let posts_dir = temp_dir.path().join("posts");
+        fs::create_dir(&posts_dir).unwrap();
+        let secret = temp_dir.path().join("secret.txt");
+        fs::write(&secret, "top secret").unwrap();
+
+        let markdown = format!("@code[{}]", secret.display());
+        let (result, diagnostics) = preprocess_with(
+            preprocess_code_includes,
+            &markdown,
+            &posts_dir,
+            &Default::default(),
+        );
+
+        assert!(!result.contains("top secret"));
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+    }
+
+    #[test]
+    fn test_preprocess_code_includes_allows_extra_roots() {
+        let temp_dir = TempDir::new().unwrap();
+        let posts_dir = temp_dir.path().join("posts");
+        let snippets_dir = temp_dir.path().join("snippets");
+        fs::create_dir(&posts_dir).unwrap();
+        fs::create_dir(&snippets_dir).unwrap();
+        fs::write(snippets_dir.join("hello.rs"), "fn main() {}").unwrap();
+
+        let options = RenderOptions {
+            include_roots: vec![posts_dir.clone(), snippets_dir],
+            ..Default::default()
+        };
+        let markdown = "@code[../snippets/hello.rs]";
+        let (result, diagnostics) =
+            preprocess_with(preprocess_code_includes, markdown, &posts_dir, &options);
+
+        assert!(result.contains("```rust"));
+        assert!(result.contains("fn main() {}"));
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_split_revision() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("user@host.txt"), "").unwrap();
+
+        assert_eq!(
+            split_revision("src/lib.rs@v1.2.0", temp_dir.path()),
+            ("src/lib.rs", Some("v1.2.0"))
+        );
+        assert_eq!(
+            split_revision("user@host.txt", temp_dir.path()),
+            ("user@host.txt", None)
+        );
+        assert_eq!(
+            split_revision("main.py", temp_dir.path()),
+            ("main.py", None)
+        );
+        assert_eq!(
+            split_revision("node_modules/@types/x.d.ts", temp_dir.path()),
+            ("node_modules/@types/x.d.ts", None)
+        );
+    }
+
+    #[test]
+    fn test_preprocess_code_includes_unknown_revision_is_an_error() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join("src")).unwrap();
+        fs::write(temp_dir.path().join("src/lib.rs"), "pub fn f() {}\n").unwrap();
+        fs::write(temp_dir.path().join("user@host.txt"), "hello").unwrap();
+        let preprocess = |markdown| {
+            preprocess_with(
+                preprocess_code_includes,
+                markdown,
+                temp_dir.path(),
+                &Default::default(),
+            )
+        };
+
+        // An existing file name containing `@` is read as-is
+        let (result, diagnostics) = preprocess("@code[user@host.txt]");
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert!(result.contains("\nhello\n"));
+
+        let (result, diagnostics) = preprocess("@code[src/lib.rs@v9.9.9]");
+        assert!(without_markers(&result).contains("<code>src/lib.rs@v9.9.9</code>"));
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+        assert!(
+            diagnostics[0]
+                .message
+                .contains("is not inside a git repository"),
+            "{}",
+            diagnostics[0].message
+        );
+
+        let status = std::process::Command::new("git")
+            .arg("-C")
+            .arg(temp_dir.path())
+            .args(["init", "-q"])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        for markdown in ["@code[src/lib.rs@v9.9.9]", "@code[notes@draft.txt]"] {
+            let (_, diagnostics) = preprocess(markdown);
+            assert_eq!(diagnostics.len(), 1, "{markdown}");
+            assert!(diagnostics[0].is_error());
+            assert!(
+                diagnostics[0].message.contains("unknown git revision"),
+                "{}",
+                diagnostics[0].message
+            );
+        }
     }
 }
@@ -0,0 +1,221 @@
+use std::ops::RangeInclusive;
+
+use crate::escape::escape_html;
+use crate::highlight::{highlighted_code_html, split_highlighted_lines};
+use crate::options::Highlighting;
+
+/// Script that copies a code block to the clipboard when its copy button is clicked.
+pub const CODE_COPY_SCRIPT: &str = r#"
+document.querySelectorAll('.code-copy').forEach((button) => {
+  button.addEventListener('click', async () => {
+    const code = button.closest('.code-block').querySelector('pre code');
+    try {
+      await navigator.clipboard.writeText(code.textContent);
+      button.textContent = 'Copied!';
+    } catch {
+      button.textContent = 'Copy failed';
+    }
+    setTimeout(() => { button.textContent = 'Copy'; }, 2000);
+  });
+});
+"#;
+
+/// A backtick fence for a code block holding `code`: longer than any run of backticks
+/// in the code, so that no line of it can close the block early.
+pub(crate) fn fence_for(code: &str) -> String {
+    let longest_run = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest_run.max(2) + 1)
+}
+
+/// Extracts `name="..."` from a code block info string, returning the value and the
+/// info string without it.
+pub(crate) fn take_attribute(info: &str, name: &str) -> (Option<String>, String) {
+    let key = format!("{name}=\"");
+    if let Some(start) = info.find(&key) {
+        let value_start = start + key.len();
+        if let Some(len) = info[value_start..].find('"') {
+            let value = info[value_start..value_start + len].to_string();
+            let rest = format!("{}{}", &info[..start], &info[value_start + len + 1..]);
+            return (
+                Some(value),
+                rest.split_whitespace().collect::<Vec<_>>().join(" "),
+            );
+        }
+    }
+    (None, info.to_string())
+}
+
+/// Options from the info string of a fenced code block, e.g.
+/// ```` ```rust title="src/main.rs" linenos hl_lines="3-5 8" ````.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct FenceInfo {
+    pub lang: String,
+    pub title: Option<String>,
+    pub line_numbers: bool,
+    pub highlighted_lines: Vec<RangeInclusive<usize>>,
+}
+
+impl FenceInfo {
+    pub(crate) fn parse(info: &str) -> Self {
+        let (title, info) = take_attribute(info, "title");
+        let (hl_lines, info) = take_attribute(&info, "hl_lines");
+        let mut words = info.split_whitespace();
+        let line_numbers = words.clone().any(|word| word == "linenos");
+        let lang = words
+            .find(|word| *word != "linenos" && !word.contains('='))
+            .unwrap_or_default()
+            .to_string();
+        FenceInfo {
+            lang,
+            title,
+            line_numbers,
+            highlighted_lines: hl_lines
+                .as_deref()
+                .map(parse_line_ranges)
+                .unwrap_or_default(),
+        }
+    }
+
+    fn is_highlighted(&self, line: usize) -> bool {
+        self.highlighted_lines.iter().any(|r| r.contains(&line))
+    }
+}
+
+/// Parses line ranges such as `3-5 8` or `3-5,8`. Invalid ranges are ignored.
+fn parse_line_ranges(spec: &str) -> Vec<RangeInclusive<usize>> {
+    spec.split([' ', ','])
+        .filter_map(|part| {
+            let (start, end) = part.split_once('-').unwrap_or((part, part));
+            let start = start.trim().parse().ok()?;
+            let end = end.trim().parse().ok()?;
+            (start <= end).then_some(start..=end)
+        })
+        .collect()
+}
+
+/// Renders `code` as `lang` for [`Highlighting::Static`], wrapping each line in a
+/// `code-line` span when line numbers or highlighted lines are requested.
+fn static_pre_html(code: &str, info: &FenceInfo) -> String {
+    let spans = highlighted_code_html(code, &info.lang);
+    let mut class = String::from("hl-code");
+    if info.line_numbers {
+        class.push_str(" line-numbers");
+    }
+    let spans = if info.line_numbers || !info.highlighted_lines.is_empty() {
+        split_highlighted_lines(&spans)
+            .iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let number = i + 1;
+                let highlighted = if info.is_highlighted(number) {
+                    " highlighted"
+                } else {
+                    ""
+                };
+                format!(
+                    r#"<span class="code-line{highlighted}" data-line="{number}">{line}</span>"#
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+            + "\n"
+    } else {
+        spans
+    };
+    format!(
+        "<pre class=\"{class}\">{}{spans}</code></pre>",
+        code_open_tag(info)
+    )
+}
+
+/// Renders `code` for Prism, whose line-numbers and line-highlight plugins read the
+/// `line-numbers` class and `data-line` attribute.
+fn prism_pre_html(code: &str, info: &FenceInfo) -> String {
+    let class = if info.line_numbers {
+        r#" class="line-numbers""#
+    } else {
+        ""
+    };
+    let data_line = if info.highlighted_lines.is_empty() {
+        String::new()
+    } else {
+        let ranges: Vec<String> = info
+            .highlighted_lines
+            .iter()
+            .map(|r| format!("{}-{}", r.start(), r.end()))
+            .collect();
+        format!(r#" data-line="{}""#, ranges.join(","))
+    };
+    format!(
+        "<pre{class}{data_line}>{}{}</code></pre>",
+        code_open_tag(info),
+        escape_html(code)
+    )
+}
+
+fn code_open_tag(info: &FenceInfo) -> String {
+    if info.lang.is_empty() {
+        "<code>".to_string()
+    } else {
+        format!("<code class=\"language-{}\">", escape_html(&info.lang))
+    }
+}
+
+/// Renders a code block with its optional filename header and a copy button.
+pub(crate) fn code_block_html(code: &str, info: &FenceInfo, highlighting: Highlighting) -> String {
+    let title = info.title.as_ref().map_or(String::new(), |title| {
+        format!(
+            "<div class=\"code-block-title\">{}</div>\n",
+            escape_html(title)
+        )
+    });
+    let pre = match highlighting {
+        Highlighting::Prism => prism_pre_html(code, info),
+        Highlighting::Static => static_pre_html(code, info),
+    };
+    format!(
+        r#"<div class="code-block">
+{title}{pre}
+<button type="button" class="code-copy" aria-label="Copy code to clipboard">Copy</button>
+</div>
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fence_info_parse() {
+        assert_eq!(
+            FenceInfo::parse(r#"rust title="src/main.rs" linenos hl_lines="3-5 8""#),
+            FenceInfo {
+                lang: "rust".to_string(),
+                title: Some("src/main.rs".to_string()),
+                line_numbers: true,
+                highlighted_lines: vec![3..=5, 8..=8],
+            }
+        );
+        assert_eq!(FenceInfo::parse("linenos").lang, "");
+        assert_eq!(
+            FenceInfo::parse(r#"py hl_lines="2,x,5-4""#).highlighted_lines,
+            vec![2..=2]
+        );
+    }
+
+    #[test]
+    fn test_code_block_html() {
+        let info = FenceInfo::parse(r#"text title="a <b>.txt" linenos hl_lines="2""#);
+        let html = code_block_html("one\ntwo\n", &info, Highlighting::Static);
+
+        assert!(html.starts_with("<div class=\"code-block\">\n<div class=\"code-block-title\">a &lt;b&gt;.txt</div>\n<pre class=\"hl-code line-numbers\"><code class=\"language-text\">"));
+        assert!(html.contains(r#"<span class="code-line highlighted" data-line="2">"#));
+        assert!(html.contains(r#"<button type="button" class="code-copy""#));
+
+        let html = code_block_html("a < b\n", &info, Highlighting::Prism);
+        assert!(html.contains(
+            r#"<pre class="line-numbers" data-line="2-2"><code class="language-text">a &lt; b"#
+        ));
+    }
+}
@@ -0,0 +1,243 @@
+use crate::code_fence::take_attribute;
+use crate::escape::escape_html;
+use crate::raw_html::generated;
+
+/// Script that turns each `.code-tabs` group into an accessible tab widget.
+///
+/// Without JavaScript the group stays a stack of labelled code blocks.
+pub const CODE_TABS_SCRIPT: &str = r#"
+document.querySelectorAll('.code-tabs').forEach((group, groupIndex) => {
+  const panels = Array.from(group.querySelectorAll(':scope > .code-tab'));
+  const tablist = document.createElement('div');
+  tablist.setAttribute('role', 'tablist');
+  const tabs = panels.map((panel, i) => {
+    const tab = document.createElement('button');
+    const id = `code-tabs-${groupIndex}-${i}`;
+    tab.type = 'button';
+    tab.id = `${id}-tab`;
+    tab.textContent = panel.dataset.label;
+    tab.setAttribute('role', 'tab');
+    tab.setAttribute('aria-controls', id);
+    panel.id = id;
+    panel.setAttribute('role', 'tabpanel');
+    panel.setAttribute('aria-labelledby', tab.id);
+    tablist.appendChild(tab);
+    return tab;
+  });
+  const select = (index) => {
+    tabs.forEach((tab, i) => {
+      tab.setAttribute('aria-selected', i === index ? 'true' : 'false');
+      tab.tabIndex = i === index ? 0 : -1;
+      panels[i].hidden = i !== index;
+    });
+  };
+  tabs.forEach((tab, i) => {
+    tab.addEventListener('click', () => select(i));
+    tab.addEventListener('keydown', (event) => {
+      const step = { ArrowRight: 1, ArrowLeft: -1 }[event.key];
+      if (step) {
+        const next = (i + step + tabs.length) % tabs.length;
+        select(next);
+        tabs[next].focus();
+      }
+    });
+  });
+  group.prepend(tablist);
+  group.classList.add('code-tabs-enhanced');
+  select(0);
+});
+"#;
+
+/// A fenced code block inside a `:::tabs` group.
+struct Tab<'a> {
+    label: String,
+    /// The code block, with `title="..."` removed from its info string.
+    block: String,
+    /// Non-code lines (e.g. an include placeholder) that preceded the block.
+    leading: Vec<&'a str>,
+}
+
+/// Returns the fence (e.g. "```" or "~~~~") that opens a fenced code block on `line`.
+fn opening_fence(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let fence_char = trimmed.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = trimmed.chars().take_while(|c| *c == fence_char).count();
+    (len >= 3).then(|| &trimmed[..len])
+}
+
+fn is_closing_fence(line: &str, fence: &str) -> bool {
+    let trimmed = line.trim();
+    let fence_char = fence.chars().next().unwrap_or('`');
+    trimmed.len() >= fence.len() && trimmed.chars().all(|c| c == fence_char)
+}
+
+/// Extracts `title="..."` from a code block info string, returning the title and the
+/// info string without it.
+pub(crate) fn take_title(info: &str) -> (Option<String>, String) {
+    take_attribute(info, "title")
+}
+
+/// Groups the fenced code blocks between `:::tabs` and `:::` into a tabbed widget.
+///
+/// Each block becomes a tab labelled by its `title="..."` attribute, or by its language.
+/// The markup is a stack of labelled blocks that [`CODE_TABS_SCRIPT`] enhances into tabs:
+///
+/// ````markdown
+/// :::tabs
+/// ```python
+/// print("hi")
+/// ```
+/// ```rust title="Rust (stable)"
+/// println!("hi");
+/// ```
+/// :::
+/// ````
+pub fn group_code_tabs(markdown: &str) -> String {
+    let lines: Vec<&str> = markdown.split('\n').collect();
+    let mut output: Vec<String> = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        if let Some(open) = fence {
+            if is_closing_fence(line, open) {
+                fence = None;
+            }
+        } else if let Some(open) = opening_fence(line) {
+            fence = Some(open);
+        } else if line.trim() == ":::tabs"
+            && let Some((tabs, end)) = parse_group(&lines, i + 1)
+        {
+            output.push(render_group(&tabs));
+            i = end + 1;
+            continue;
+        }
+        output.push(line.to_string());
+        i += 1;
+    }
+
+    output.join("\n")
+}
+
+/// Parses the tabs of a group starting at `start`, returning them and the index of the closing `:::`.
+fn parse_group<'a>(lines: &[&'a str], start: usize) -> Option<(Vec<Tab<'a>>, usize)> {
+    let mut tabs: Vec<Tab<'a>> = Vec::new();
+    let mut leading = Vec::new();
+    let mut i = start;
+
+    while i < lines.len() {
+        let line = lines[i];
+        if line.trim() == ":::" {
+            if tabs.is_empty() {
+                return None;
+            }
+            if let Some(last) = tabs.last_mut() {
+                last.block.push_str(&trailing(&leading));
+            }
+            return Some((tabs, i));
+        }
+
+        let Some(open) = opening_fence(line) else {
+            if !line.trim().is_empty() {
+                leading.push(line);
+            }
+            i += 1;
+            continue;
+        };
+
+        let info = line.trim_start()[open.len()..].trim();
+        let (title, info) = take_title(info);
+        let lang = info.split_whitespace().next().unwrap_or_default();
+        let label = title.unwrap_or_else(|| {
+            if lang.is_empty() {
+                "Code".to_string()
+            } else {
+                lang.to_string()
+            }
+        });
+
+        let mut block = format!("{open}{info}\n");
+        i += 1;
+        while i < lines.len() && !is_closing_fence(lines[i], open) {
+            block.push_str(lines[i]);
+            block.push('\n');
+            i += 1;
+        }
+        if i == lines.len() {
+            return None;
+        }
+        block.push_str(open);
+        i += 1;
+
+        tabs.push(Tab {
+            label,
+            block,
+            leading: std::mem::take(&mut leading),
+        });
+    }
+
+    None
+}
+
+fn trailing(lines: &[&str]) -> String {
+    lines.iter().map(|line| format!("\n\n{line}")).collect()
+}
+
+fn render_group(tabs: &[Tab]) -> String {
+    let mut html = generated("<div class=\"code-tabs\">\n");
+    for tab in tabs {
+        let label = escape_html(&tab.label);
+        html.push_str(&generated(&format!(
+            "<div class=\"code-tab\" data-label=\"{label}\">\n<p class=\"code-tab-label\">{label}</p>\n\n"
+        )));
+        for line in &tab.leading {
+            html.push_str(line);
+            html.push_str("\n\n");
+        }
+        html.push_str(&tab.block);
+        html.push_str("\n\n</div>\n");
+    }
+    html.push_str("</div>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_html::without_markers;
+
+    #[test]
+    fn test_group_code_tabs() {
+        let markdown = "Intro\n\n:::tabs\n```python\nprint('hi')\n```\n\n```rust title=\"Rust <stable>\"\nfn main() {}\n```\n:::\n\nOutro";
+        let result = group_code_tabs(markdown);
+        let result = without_markers(&result);
+
+        assert!(result.starts_with("Intro\n\n<div class=\"code-tabs\">\n"));
+        assert!(result.contains(
+            "<div class=\"code-tab\" data-label=\"python\">\n<p class=\"code-tab-label\">python</p>\n\n```python\nprint('hi')\n```\n\n</div>"
+        ));
+        assert!(result.contains("data-label=\"Rust &lt;stable&gt;\""));
+        assert!(result.contains("```rust\nfn main() {}\n```"));
+        assert!(!result.contains(":::"));
+        assert!(result.ends_with("</div>\n\nOutro"));
+    }
+
+    #[test]
+    fn test_group_code_tabs_ignores_markers_in_code_and_unclosed_groups() {
+        let in_code = "```text\n:::tabs\n```\n";
+        assert_eq!(group_code_tabs(in_code), in_code);
+
+        let unclosed = ":::tabs\n```python\nprint('hi')\n```\n";
+        assert_eq!(group_code_tabs(unclosed), unclosed);
+    }
+
+    #[test]
+    fn test_take_title() {
+        assert_eq!(
+            take_title("rust title=\"src/main.rs\" linenos"),
+            (Some("src/main.rs".to_string()), "rust linenos".to_string())
+        );
+        assert_eq!(take_title("python"), (None, "python".to_string()));
+    }
+}
@@ -0,0 +1,63 @@
+use crate::escape::escape_html;
+use crate::svg_icon::Icon;
+
+/// `<head>` script that applies the colour scheme picked with the aside toggle.
+///
+/// The choice is stored in `localStorage` and set as `data-theme` on `<html>`, which
+/// the stylesheet and the `data-color-scheme` stylesheet links follow. Without a stored
+/// choice (or without JavaScript) the page follows `prefers-color-scheme`. It must come
+/// after the stylesheet links so that it runs before the page is first painted.
+pub const COLOR_SCHEME_SCRIPT: &str = r#"
+(() => {
+  const root = document.documentElement;
+  const system = window.matchMedia('(prefers-color-scheme: dark)');
+  const apply = (scheme) => {
+    if (!scheme) return;
+    root.dataset.theme = scheme;
+    document.querySelectorAll('link[data-color-scheme]').forEach((link) => {
+      link.media = link.dataset.colorScheme === scheme ? 'all' : 'not all';
+    });
+  };
+  apply(localStorage.getItem('theme'));
+  document.addEventListener('click', (event) => {
+    if (!event.target.closest('.theme-toggle')) return;
+    const current = root.dataset.theme || (system.matches ? 'dark' : 'light');
+    const next = current === 'dark' ? 'light' : 'dark';
+    localStorage.setItem('theme', next);
+    apply(next);
+  });
+})();
+"#;
+
+/// Renders a pair of stylesheet links of which only the one matching the active colour
+/// scheme applies.
+pub fn color_scheme_stylesheets_html(light_href: &str, dark_href: &str) -> String {
+    format!(
+        r#"<link rel="stylesheet" href="{light}" media="(prefers-color-scheme: light)" data-color-scheme="light">
+  <link rel="stylesheet" href="{dark}" media="(prefers-color-scheme: dark)" data-color-scheme="dark">"#,
+        light = escape_html(light_href),
+        dark = escape_html(dark_href)
+    )
+}
+
+/// Renders the button that switches between the light and dark colour schemes.
+pub(crate) fn color_scheme_toggle_html() -> String {
+    format!(
+        r#"<button type="button" class="icon-button theme-toggle" aria-label="Toggle dark mode">{moon}{sun}</button>"#,
+        moon = Icon::Moon.svg(),
+        sun = Icon::Sun.svg()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_scheme_stylesheets_html() {
+        let html = color_scheme_stylesheets_html("light.css", "dark.css");
+
+        assert!(html.starts_with(r#"<link rel="stylesheet" href="light.css" media="(prefers-color-scheme: light)" data-color-scheme="light">"#));
+        assert!(html.ends_with(r#"<link rel="stylesheet" href="dark.css" media="(prefers-color-scheme: dark)" data-color-scheme="dark">"#));
+    }
+}
@@ -0,0 +1,112 @@
+use similar::TextDiff;
+
+use crate::code_fence::fence_for;
+use crate::diagnostic::Diagnostic;
+use crate::directive::{IncludeContext, missing_include_html, read_include};
+
+/// Number of unchanged lines shown around each change by default.
+const DEFAULT_CONTEXT: usize = 3;
+
+/// Computes a unified line diff between `old` and `new`.
+pub fn unified_diff(
+    old: &str,
+    new: &str,
+    old_name: &str,
+    new_name: &str,
+    context: usize,
+) -> String {
+    let diff = TextDiff::from_lines(old, new);
+    let mut unified = diff.unified_diff();
+    unified
+        .context_radius(context)
+        .missing_newline_hint(false)
+        .header(old_name, new_name);
+    unified.to_string()
+}
+
+/// Expands a single `@diff[old.rs, new.rs]` directive into a fenced `diff` code block.
+///
+/// Both paths are resolved like `@code[...]`. An optional `context=N` after the second
+/// path sets how many unchanged lines surround each change, e.g.
+/// `@diff[before.rs, after.rs context=10]`.
+pub(crate) fn expand_diff_include(
+    arg: &str,
+    line: usize,
+    ctx: &IncludeContext,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> String {
+    let Some((old_path, rest)) = arg.split_once(',') else {
+        diagnostics.push(Diagnostic::warning(
+            ctx.path,
+            line,
+            format!("`@diff[{arg}]` expects two files: @diff[old, new]"),
+        ));
+        return missing_include_html("diff", arg);
+    };
+    let old_path = old_path.trim();
+    let mut parts = rest.split_whitespace();
+    let new_path = parts.next().unwrap_or_default();
+
+    let mut context = DEFAULT_CONTEXT;
+    for option in parts {
+        match option.strip_prefix("context=").and_then(|n| n.parse().ok()) {
+            Some(n) => context = n,
+            None => diagnostics.push(Diagnostic::warning(
+                ctx.path,
+                line,
+                format!("`@diff[{arg}]`: unknown option `{option}`"),
+            )),
+        }
+    }
+
+    let old = read_include("diff", old_path, line, ctx, diagnostics);
+    let new = read_include("diff", new_path, line, ctx, diagnostics);
+    let (Some((_, old)), Some((_, new))) = (old, new) else {
+        return missing_include_html("diff", arg);
+    };
+
+    let diff = unified_diff(&old, &new, old_path, new_path, context);
+    let diff = diff.trim_end_matches('\n');
+    let fence = fence_for(diff);
+    format!("{fence}diff\n{diff}\n{fence}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::RenderOptions;
+    use crate::test_fixtures::preprocess;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_unified_diff_marks_added_and_removed_lines() {
+        let old = "fn main() {\n    println!(\"hi\");\n}\n";
+        let new = "fn main() {\n    let name = \"mejiro\";\n    println!(\"hi {name}\");\n}\n";
+        let diff = unified_diff(old, new, "old.rs", "new.rs", 3);
+
+        assert!(diff.starts_with("--- old.rs\n+++ new.rs\n@@ -1,3 +1,4 @@\n"));
+        assert!(diff.contains("\n fn main() {\n"));
+        assert!(diff.contains("\n-    println!(\"hi\");\n"));
+        assert!(diff.contains("\n+    let name = \"mejiro\";\n"));
+        assert!(diff.contains("\n+    println!(\"hi {name}\");\n"));
+    }
+
+    #[test]
+    fn test_diff_directive_renders_diff_block() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("old.py"), "a = 1\nb = 2\n").unwrap();
+        fs::write(temp_dir.path().join("new.py"), "a = 1\nb = 3\n").unwrap();
+
+        let (result, diagnostics) = preprocess(
+            "@diff[old.py, new.py]",
+            temp_dir.path(),
+            &RenderOptions::default(),
+        );
+
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert!(result.starts_with("```diff\n--- old.py\n+++ new.py\n"));
+        assert!(result.contains("\n-b = 2\n+b = 3\n"));
+        assert!(result.ends_with("\n```"));
+    }
+}
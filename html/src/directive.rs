@@ -0,0 +1,309 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::code_block::{expand_code_include, expand_notebook_include};
+use crate::diagnostic::Diagnostic;
+use crate::diff::expand_diff_include;
+use crate::escape::escape_html;
+use crate::include::expand_markdown_include;
+use crate::options::RenderOptions;
+use crate::prose::map_prose_at;
+use crate::raw_html::generated;
+use crate::run::expand_run_include;
+use crate::table::expand_table_include;
+
+/// The markdown file whose directives are being expanded.
+///
+/// Used to resolve relative include paths and to attribute diagnostics to a file and line.
+pub struct IncludeContext<'a> {
+    /// Path of the markdown file being preprocessed.
+    pub path: &'a Path,
+    /// Number of lines (e.g. the front matter) that precede the markdown being preprocessed.
+    pub line_offset: usize,
+    pub options: &'a RenderOptions,
+    /// Canonical paths of the files that (transitively) included this one, outermost first.
+    pub parents: &'a [PathBuf],
+}
+
+impl IncludeContext<'_> {
+    /// Directory that relative include paths are resolved against.
+    pub fn base_dir(&self) -> &Path {
+        self.path.parent().unwrap_or_else(|| Path::new("."))
+    }
+
+    /// Returns the 1-based line number in the source file of byte `pos` in `markdown`.
+    pub fn line_of(&self, markdown: &str, pos: usize) -> usize {
+        self.line_offset + markdown[..pos].matches('\n').count() + 1
+    }
+}
+
+/// Why an include path could not be used.
+#[derive(Debug)]
+pub(crate) enum IncludePathError {
+    NotFound,
+    OutsideRoots,
+}
+
+/// Resolves an include path against `base_dir` and checks that it stays inside one of
+/// the allowed `roots`.
+///
+/// The resolved path is canonicalized, so `..` segments, absolute paths and symlinks
+/// that point outside every root are rejected. When `roots` is empty, the include is
+/// limited to `base_dir` itself.
+pub(crate) fn resolve_include_path(
+    base_dir: &Path,
+    file_path: &str,
+    roots: &[PathBuf],
+) -> Result<PathBuf, IncludePathError> {
+    let full_path = base_dir
+        .join(file_path)
+        .canonicalize()
+        .map_err(|_| IncludePathError::NotFound)?;
+
+    if is_within_roots(base_dir, &full_path, roots) {
+        Ok(full_path)
+    } else {
+        Err(IncludePathError::OutsideRoots)
+    }
+}
+
+/// Like [`resolve_include_path`], but for paths that need not exist in the working tree
+/// (e.g. a file read from an older git revision).
+///
+/// `..` segments are resolved lexically against the canonical `base_dir` before the
+/// containment check.
+pub(crate) fn resolve_virtual_include_path(
+    base_dir: &Path,
+    file_path: &str,
+    roots: &[PathBuf],
+) -> Result<PathBuf, IncludePathError> {
+    let base_dir = base_dir
+        .canonicalize()
+        .map_err(|_| IncludePathError::NotFound)?;
+
+    let mut full_path = PathBuf::new();
+    for component in base_dir.join(file_path).components() {
+        match component {
+            Component::ParentDir => {
+                full_path.pop();
+            }
+            Component::CurDir => {}
+            component => full_path.push(component),
+        }
+    }
+
+    if is_within_roots(&base_dir, &full_path, roots) {
+        Ok(full_path)
+    } else {
+        Err(IncludePathError::OutsideRoots)
+    }
+}
+
+/// Checks that the canonical `full_path` lives in one of `roots` (or in `base_dir` if there are none).
+fn is_within_roots(base_dir: &Path, full_path: &Path, roots: &[PathBuf]) -> bool {
+    let default_roots = [base_dir.to_path_buf()];
+    let roots = if roots.is_empty() {
+        &default_roots[..]
+    } else {
+        roots
+    };
+
+    roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| full_path.starts_with(root))
+}
+
+/// Reads an included file, reporting problems as diagnostics.
+///
+/// A path outside the include roots is always an error. A missing or unreadable file is a
+/// warning; the caller renders [`missing_include_html`] in its place.
+pub(crate) fn read_include(
+    directive: &str,
+    file_path: &str,
+    line: usize,
+    ctx: &IncludeContext,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<(PathBuf, String)> {
+    let full_path =
+        match resolve_include_path(ctx.base_dir(), file_path, &ctx.options.include_roots) {
+            Ok(path) => path,
+            Err(IncludePathError::OutsideRoots) => {
+                diagnostics.push(Diagnostic::error(
+                ctx.path,
+                line,
+                format!(
+                    "`@{directive}[{file_path}]` resolves outside the allowed include directories"
+                ),
+            ));
+                return None;
+            }
+            Err(IncludePathError::NotFound) => {
+                diagnostics.push(Diagnostic::warning(
+                    ctx.path,
+                    line,
+                    format!("`@{directive}` file not found: {file_path}"),
+                ));
+                return None;
+            }
+        };
+
+    match fs::read_to_string(&full_path) {
+        Ok(content) => Some((full_path, content)),
+        Err(e) => {
+            diagnostics.push(Diagnostic::warning(
+                ctx.path,
+                line,
+                format!("`@{directive}` could not read {file_path}: {e}"),
+            ));
+            None
+        }
+    }
+}
+
+/// Visible placeholder rendered in place of an include that could not be read.
+pub(crate) fn missing_include_html(directive: &str, file_path: &str) -> String {
+    let file_path = escape_html(file_path);
+    generated(&format!(
+        "<div class=\"include-missing\">Missing <code>@{directive}</code> include: <code>{file_path}</code></div>\n"
+    ))
+}
+
+/// Expands every `@name[argument]` directive in `markdown` in a single pass.
+///
+/// `expand` receives the directive name, its argument and the byte offset of the `@`, and
+/// returns the replacement text, or `None` to leave the directive as-is. The replacement is
+/// not scanned again, so included content cannot smuggle in new directives. Directives
+/// inside code blocks and inline code are left alone, so they can be shown in examples.
+pub(crate) fn expand_directives(
+    markdown: &str,
+    mut expand: impl FnMut(&str, &str, usize) -> Option<String>,
+) -> String {
+    map_prose_at(markdown, |text, offset| {
+        expand_prose_directives(text, |name, arg, pos| expand(name, arg, offset + pos))
+    })
+}
+
+/// Expands the directives in `markdown`, which contains no code.
+fn expand_prose_directives(
+    markdown: &str,
+    mut expand: impl FnMut(&str, &str, usize) -> Option<String>,
+) -> String {
+    let mut result = String::new();
+    let mut last_pos = 0;
+    let mut search_pos = 0;
+
+    while let Some(at) = markdown[search_pos..].find('@') {
+        let start_pos = search_pos + at;
+        search_pos = start_pos + 1;
+
+        // Directive names are lowercase ASCII letters followed by `[`
+        let rest = &markdown[start_pos + 1..];
+        let name_len = rest
+            .find(|c: char| !c.is_ascii_lowercase())
+            .unwrap_or(rest.len());
+        if name_len == 0 || !rest[name_len..].starts_with('[') {
+            continue;
+        }
+        let name = &rest[..name_len];
+        let arg_start = start_pos + 1 + name_len + 1;
+
+        // Find the closing ] on the same line
+        let line_end = markdown[arg_start..]
+            .find('\n')
+            .map_or(markdown.len(), |i| arg_start + i);
+        let Some(end) = markdown[arg_start..line_end].find(']') else {
+            continue;
+        };
+        let end_pos = arg_start + end;
+
+        if let Some(replacement) = expand(name, &markdown[arg_start..end_pos], start_pos) {
+            // Append everything before the directive, then its replacement
+            result.push_str(&markdown[last_pos..start_pos]);
+            result.push_str(&replacement);
+            last_pos = end_pos + 1;
+            search_pos = last_pos;
+        }
+    }
+
+    // Append the remaining content
+    result.push_str(&markdown[last_pos..]);
+
+    result
+}
+
+/// Expands all supported directives (`@code[...]`, `@include[...]`, `@notebook[...]`,
+/// `@table[...]`, `@diff[...]`, `@run[...]`) in `markdown`.
+pub fn preprocess_directives(
+    markdown: &str,
+    ctx: &IncludeContext,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> String {
+    expand_directives(markdown, |name, arg, pos| {
+        let line = ctx.line_of(markdown, pos);
+        match name {
+            "code" => Some(expand_code_include(arg, line, ctx, diagnostics)),
+            "include" => Some(expand_markdown_include(arg, line, ctx, diagnostics)),
+            "notebook" => Some(expand_notebook_include(arg, line, ctx, diagnostics)),
+            "table" => Some(expand_table_include(arg, line, ctx, diagnostics)),
+            "diff" => Some(expand_diff_include(arg, line, ctx, diagnostics)),
+            "run" => Some(expand_run_include(arg, line, ctx, diagnostics)),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_directives_skips_unknown_and_unclosed() {
+        let markdown = "mail me@example.com\n@note[x]\n@code[open\n]";
+        let result = expand_directives(markdown, |name, _, _| (name == "code").then(String::new));
+
+        assert_eq!(result, markdown);
+    }
+
+    #[test]
+    fn test_expand_directives_passes_argument_and_offset() {
+        let markdown = "a @code[x.rs] b @code[y.rs]";
+        let mut seen = Vec::new();
+        let result = expand_directives(markdown, |name, arg, pos| {
+            seen.push((name.to_string(), arg.to_string(), pos));
+            Some(format!("<{arg}>"))
+        });
+
+        assert_eq!(result, "a <x.rs> b <y.rs>");
+        assert_eq!(
+            seen,
+            vec![
+                ("code".to_string(), "x.rs".to_string(), 2),
+                ("code".to_string(), "y.rs".to_string(), 16),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expand_directives_skips_code() {
+        let markdown =
+            "`@code[a.txt]` @code[b.txt]\n\n```\n@run[c.sh]\n```\n\n    @run[d.sh]\n\n@code[e.txt]";
+        let mut seen = Vec::new();
+        let result = expand_directives(markdown, |_, arg, pos| {
+            seen.push((arg.to_string(), pos));
+            Some("X".to_string())
+        });
+
+        assert_eq!(
+            result,
+            "`@code[a.txt]` X\n\n```\n@run[c.sh]\n```\n\n    @run[d.sh]\n\nX"
+        );
+        assert_eq!(
+            seen,
+            vec![
+                ("b.txt".to_string(), 15),
+                ("e.txt".to_string(), markdown.find("@code[e").unwrap()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use pulldown_cmark::{Parser, html};
+
+use crate::directive::expand_directives;
+use crate::escape::escape_html;
+use crate::include::slugify;
+use crate::prose::code_ranges;
+use crate::raw_html::generated;
+
+/// A numbered figure found in a post.
+#[derive(Debug, PartialEq)]
+struct Figure {
+    id: String,
+    src: String,
+    alt: String,
+    caption: String,
+}
+
+/// Parses the argument of `@figure[src "Caption" #id]`. The caption and id are optional.
+fn parse_figure_directive(arg: &str) -> Option<(String, String, Option<String>)> {
+    let arg = arg.trim();
+    let (src, rest) = arg.split_once(char::is_whitespace).unwrap_or((arg, ""));
+    if src.is_empty() {
+        return None;
+    }
+
+    let mut rest = rest.trim();
+    let mut caption = String::new();
+    if let Some(quoted) = rest.strip_prefix('"') {
+        let end = quoted.find('"')?;
+        caption = quoted[..end].to_string();
+        rest = quoted[end + 1..].trim();
+    }
+
+    let id = match rest.strip_prefix('#') {
+        Some(id) if !id.is_empty() => Some(id.to_string()),
+        Some(_) => return None,
+        None if rest.is_empty() => None,
+        None => return None,
+    };
+
+    Some((src.to_string(), caption, id))
+}
+
+/// Parses a line that consists only of a markdown image, `![alt](src)`.
+fn parse_image_line(line: &str) -> Option<(String, String)> {
+    let rest = line.trim().strip_prefix("![")?;
+    let (alt, rest) = rest.split_once("](")?;
+    let src = rest.strip_suffix(')')?;
+    (!src.is_empty() && !src.contains(char::is_whitespace))
+        .then(|| (alt.to_string(), src.to_string()))
+}
+
+/// Parses a caption line written in italics, `*Caption*` or `_Caption_`.
+fn parse_caption_line(line: &str) -> Option<String> {
+    let line = line.trim();
+    ['*', '_'].iter().find_map(|marker| {
+        let caption = line.strip_prefix(*marker)?.strip_suffix(*marker)?;
+        (!caption.is_empty() && !caption.starts_with(*marker)).then(|| caption.to_string())
+    })
+}
+
+/// Renders inline markdown (e.g. a caption) to HTML without the surrounding paragraph.
+fn render_inline(markdown: &str) -> String {
+    let mut output = String::new();
+    html::push_html(&mut output, Parser::new(markdown));
+    let output = output.trim_end();
+    output
+        .strip_prefix("<p>")
+        .and_then(|s| s.strip_suffix("</p>"))
+        .unwrap_or(output)
+        .to_string()
+}
+
+fn default_figure_id(src: &str) -> String {
+    let stem = src
+        .rsplit('/')
+        .next()
+        .unwrap_or(src)
+        .split('.')
+        .next()
+        .unwrap_or_default();
+    format!("fig-{}", slugify(stem))
+}
+
+fn render_figure(figure: &Figure, number: usize) -> String {
+    let id = escape_html(&figure.id);
+    let src = escape_html(&figure.src);
+    let alt = escape_html(&figure.alt);
+    let caption = if figure.caption.is_empty() {
+        format!("Figure {number}")
+    } else {
+        format!("Figure {number}: {}", render_inline(&figure.caption))
+    };
+    // The caption may contain raw HTML, which the `raw_html` policy still applies to
+    format!(
+        "{}{caption}</figcaption>\n</figure>",
+        generated(&format!(
+            "<figure id=\"{id}\">\n<img src=\"{src}\" alt=\"{alt}\">\n<figcaption>"
+        ))
+    )
+}
+
+/// Numbers `figure` after the figures seen so far and renders it.
+fn add_figure(figures: &mut Vec<Figure>, mut figure: Figure) -> String {
+    // Keep ids unique so every figure stays referenceable
+    let base = figure.id.clone();
+    let mut n = 2;
+    while figures.iter().any(|f| f.id == figure.id) {
+        figure.id = format!("{base}-{n}");
+        n += 1;
+    }
+    let html = render_figure(&figure, figures.len() + 1);
+    figures.push(figure);
+    html
+}
+
+/// Turns `@figure[...]` directives, and images directly followed by an italic caption
+/// line, into numbered `<figure>` elements, then resolves `@ref[fig-id]` into
+/// "Figure N" links.
+///
+/// ```markdown
+/// @figure[../images/plot.png "Latency by payload size" #fig-latency]
+///
+/// ![Architecture](../images/arch.png)
+/// *How requests flow through the system*
+///
+/// As @ref[fig-latency] shows, ...
+/// ```
+///
+/// Figures without an explicit `#id` get `fig-<image file stem>`. Figures are numbered in
+/// document order. Returns the markdown and the ids of `@ref`s that matched no figure.
+pub fn number_figures(markdown: &str) -> (String, Vec<String>) {
+    let mut figures: Vec<Figure> = Vec::new();
+    let mut output: Vec<String> = Vec::new();
+    let lines: Vec<&str> = markdown.split('\n').collect();
+    let code = code_ranges(markdown);
+    let mut line_start = 0;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let text_start = line_start + line.len() - line.trim_start().len();
+        line_start += line.len() + 1;
+        if code.iter().any(|range| range.contains(&text_start)) {
+            output.push(line.to_string());
+            i += 1;
+            continue;
+        }
+
+        let directive = line
+            .trim()
+            .strip_prefix("@figure[")
+            .and_then(|rest| rest.strip_suffix(']'))
+            .and_then(parse_figure_directive);
+        if let Some((src, caption, id)) = directive {
+            let figure = Figure {
+                id: id.unwrap_or_else(|| default_figure_id(&src)),
+                alt: caption.clone(),
+                src,
+                caption,
+            };
+            output.push(add_figure(&mut figures, figure));
+            i += 1;
+            continue;
+        }
+
+        if let Some((alt, src)) = parse_image_line(line)
+            && let Some(caption) = lines.get(i + 1).and_then(|next| parse_caption_line(next))
+        {
+            let figure = Figure {
+                id: default_figure_id(&src),
+                src,
+                alt,
+                caption,
+            };
+            output.push(add_figure(&mut figures, figure));
+            line_start += lines[i + 1].len() + 1;
+            i += 2;
+            continue;
+        }
+
+        output.push(line.to_string());
+        i += 1;
+    }
+
+    let mut unknown = Vec::new();
+    let markdown = expand_directives(&output.join("\n"), |name, id, _| {
+        if name != "ref" {
+            return None;
+        }
+        match figures.iter().position(|figure| figure.id == id.trim()) {
+            Some(index) => Some(generated(&format!(
+                "<a href=\"#{id}\" class=\"figure-ref\">Figure {number}</a>",
+                id = escape_html(&figures[index].id),
+                number = index + 1
+            ))),
+            None => {
+                unknown.push(id.trim().to_string());
+                None
+            }
+        }
+    });
+
+    (markdown, unknown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_html::without_markers;
+
+    #[test]
+    fn test_number_figures_and_refs() {
+        let markdown = "See @ref[fig-arch] and @ref[fig-latency].\n\n@figure[../images/latency.png \"Latency *by* size\" #fig-latency]\n\n![Architecture](../images/arch.png)\n*How requests flow*\n\n```text\n@figure[ignored.png]\n```\n\n    @figure[indented.png]";
+        let (result, unknown) = number_figures(markdown);
+        let result = without_markers(&result);
+
+        assert!(unknown.is_empty());
+        assert!(result.starts_with(
+            "See <a href=\"#fig-arch\" class=\"figure-ref\">Figure 2</a> and <a href=\"#fig-latency\" class=\"figure-ref\">Figure 1</a>."
+        ));
+        assert!(result.contains(
+            "<figure id=\"fig-latency\">\n<img src=\"../images/latency.png\" alt=\"Latency *by* size\">\n<figcaption>Figure 1: Latency <em>by</em> size</figcaption>\n</figure>"
+        ));
+        assert!(result.contains(
+            "<figure id=\"fig-arch\">\n<img src=\"../images/arch.png\" alt=\"Architecture\">\n<figcaption>Figure 2: How requests flow</figcaption>\n</figure>"
+        ));
+        assert!(result.contains("```text\n@figure[ignored.png]\n```"));
+        assert!(result.ends_with("    @figure[indented.png]"));
+    }
+
+    #[test]
+    fn test_number_figures_reports_unknown_refs_and_dedupes_ids() {
+        let markdown =
+            "@figure[a/plot.png]\n@figure[b/plot.png]\n\n@ref[fig-plot-2] @ref[fig-missing]";
+        let (result, unknown) = number_figures(markdown);
+        let result = without_markers(&result);
+
+        assert!(result.contains("<figure id=\"fig-plot\">"));
+        assert!(result.contains("<figure id=\"fig-plot-2\">"));
+        assert!(result.contains("<figcaption>Figure 2</figcaption>"));
+        assert!(result.contains("<a href=\"#fig-plot-2\" class=\"figure-ref\">Figure 2</a>"));
+        assert!(result.contains("@ref[fig-missing]"));
+        assert_eq!(unknown, vec!["fig-missing".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_figure_directive() {
+        assert_eq!(
+            parse_figure_directive("images/plot.png \"Caption text\""),
+            Some((
+                "images/plot.png".to_string(),
+                "Caption text".to_string(),
+                None
+            ))
+        );
+        assert_eq!(
+            parse_figure_directive("plot.png #fig-x"),
+            Some((
+                "plot.png".to_string(),
+                String::new(),
+                Some("fig-x".to_string())
+            ))
+        );
+        assert_eq!(parse_figure_directive("plot.png \"unclosed"), None);
+    }
+}
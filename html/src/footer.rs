@@ -1,5 +1,7 @@
 use chrono::Datelike;
 
+use crate::escape::escape_html;
+
 pub fn footer_html(site_title: &str) -> String {
     let current_year = chrono::Utc::now().year();
     format!(
@@ -7,6 +9,7 @@ pub fn footer_html(site_title: &str) -> String {
 <footer>
     <p>&copy; {current_year} {site_title}</p>
 </footer>
-"#
+"#,
+        site_title = escape_html(site_title)
     )
 }
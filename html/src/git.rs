@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Runs `git -C <dir> <args>` and returns its trimmed stdout, or its stderr on failure.
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("could not run git: {e}"))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+/// Reads the contents of `path` as it was at `revision` (a tag, branch or commit sha)
+/// in the git repository containing `path`.
+///
+/// `path` must be absolute. Returns a human readable error if the repository, the
+/// revision or the path at that revision does not exist.
+pub fn read_file_at_revision(path: &Path, revision: &str) -> Result<String, String> {
+    let dir = existing_ancestor(path);
+
+    let toplevel = git(&dir, &["rev-parse", "--show-toplevel"])
+        .map_err(|_| format!("{} is not inside a git repository", dir.display()))?;
+    let toplevel = PathBuf::from(toplevel.trim())
+        .canonicalize()
+        .map_err(|e| e.to_string())?;
+
+    let commit = format!("{revision}^{{commit}}");
+    git(&toplevel, &["rev-parse", "--verify", "--quiet", &commit])
+        .map_err(|_| format!("unknown git revision `{revision}`"))?;
+
+    let relative = path
+        .strip_prefix(&toplevel)
+        .map_err(|_| format!("{} is outside the git repository", path.display()))?;
+    let relative: Vec<String> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    let relative = relative.join("/");
+
+    git(&toplevel, &["show", &format!("{revision}:{relative}")])
+        .map_err(|_| format!("`{relative}` does not exist at revision `{revision}`"))
+}
+
+/// Returns the closest ancestor of `path` that exists, so git can be run from there even
+/// if the file was deleted from the working tree.
+fn existing_ancestor(path: &Path) -> PathBuf {
+    path.ancestors()
+        .skip(1)
+        .find(|dir| dir.is_dir())
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn run_git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args([
+                "-c",
+                "user.name=mejiro",
+                "-c",
+                "user.email=mejiro@example.com",
+            ])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    #[test]
+    fn test_read_file_at_revision() {
+        let temp_dir = TempDir::new().unwrap();
+        let repo = temp_dir.path().canonicalize().unwrap();
+        fs::create_dir(repo.join("src")).unwrap();
+        let file = repo.join("src").join("lib.rs");
+
+        run_git(&repo, &["init", "-q"]);
+        fs::write(&file, "pub fn v1() {}\n").unwrap();
+        run_git(&repo, &["add", "."]);
+        run_git(&repo, &["commit", "-q", "-m", "v1"]);
+        run_git(&repo, &["tag", "v1.0.0"]);
+        fs::write(&file, "pub fn v2() {}\n").unwrap();
+
+        assert_eq!(
+            read_file_at_revision(&file, "v1.0.0").unwrap(),
+            "pub fn v1() {}\n"
+        );
+
+        let err = read_file_at_revision(&file, "v9.9.9").unwrap_err();
+        assert!(err.contains("unknown git revision `v9.9.9`"), "{err}");
+
+        let err = read_file_at_revision(&repo.join("src/missing.rs"), "v1.0.0").unwrap_err();
+        assert!(err.contains("does not exist at revision"), "{err}");
+    }
+
+    #[test]
+    fn test_read_file_at_revision_outside_repository() {
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("lib.rs");
+
+        let err = read_file_at_revision(&file, "HEAD").unwrap_err();
+        assert!(err.contains("is not inside a git repository"), "{err}");
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+use syntect::highlighting::ThemeSet;
+use syntect::html::{ClassStyle, ClassedHTMLGenerator, css_for_theme_with_class_style};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+use crate::color_scheme::color_scheme_stylesheets_html;
+use crate::escape::escape_html;
+
+/// Prefix of the CSS classes emitted for highlighted code, e.g. `hl-keyword`.
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "hl-" };
+
+/// Themes from the Prism distribution, e.g. `prism` or `prism-tomorrow`.
+pub const PRISM_THEMES: [&str; 8] = [
+    "prism",
+    "prism-coy",
+    "prism-dark",
+    "prism-funky",
+    "prism-okaidia",
+    "prism-solarizedlight",
+    "prism-tomorrow",
+    "prism-twilight",
+];
+
+pub(crate) const PRISM_CDN: &str = "https://cdn.jsdelivr.net/npm/prismjs@1.29.0";
+
+/// Prism files loaded by every page, besides the themes.
+const PRISM_FILES: [&str; 6] = [
+    "plugins/line-numbers/prism-line-numbers.min.css",
+    "plugins/line-highlight/prism-line-highlight.min.css",
+    "components/prism-core.min.js",
+    "plugins/autoloader/prism-autoloader.min.js",
+    "plugins/line-numbers/prism-line-numbers.min.js",
+    "plugins/line-highlight/prism-line-highlight.min.js",
+];
+
+/// Subresource Integrity hashes of the files of the pinned Prism release, by path
+/// under [`PRISM_CDN`]. Browsers refuse a file whose content does not match, so a
+/// compromised CDN cannot change them. Every path of [`prism_paths`] needs an entry;
+/// run the ignored `test_prism_integrity_is_pinned` (which needs network access) to
+/// print the table for the pinned release.
+const PRISM_INTEGRITY: &[(&str, &str)] = &[];
+
+/// Paths of every Prism file a page may load, under [`PRISM_CDN`].
+fn prism_paths() -> Vec<String> {
+    PRISM_THEMES
+        .iter()
+        .map(|theme| format!("themes/{theme}.min.css"))
+        .chain(PRISM_FILES.iter().map(|path| path.to_string()))
+        .collect()
+}
+
+/// URLs of every Prism file a page may load.
+pub(crate) fn prism_urls() -> Vec<String> {
+    prism_paths()
+        .iter()
+        .map(|path| format!("{PRISM_CDN}/{path}"))
+        .collect()
+}
+
+/// The pinned integrity hashes of the Prism files, by URL, as used by
+/// [`crate::security::add_integrity`]. Languages fetched later by the autoloader are
+/// not covered, since browsers offer no way to check them.
+pub fn prism_integrities() -> BTreeMap<String, String> {
+    PRISM_INTEGRITY
+        .iter()
+        .map(|(path, integrity)| (format!("{PRISM_CDN}/{path}"), integrity.to_string()))
+        .collect()
+}
+
+/// The inline script that points the autoloader at the CDN and highlights the page.
+pub(crate) fn prism_init_script() -> String {
+    format!(
+        r#"
+    // Load languages on demand from the CDN
+    if (window.Prism && Prism.plugins.autoloader) {{
+      Prism.plugins.autoloader.languages_path = "{PRISM_CDN}/components/";
+    }}
+    document.addEventListener("DOMContentLoaded", () => Prism.highlightAll());
+  "#
+    )
+}
+
+/// Returns the `<head>` markup that loads Prism from the CDN to highlight code in the
+/// browser, with the plugins for line numbers and highlighted lines. `light` and `dark`
+/// are the themes for the light and dark colour schemes.
+pub fn prism_head_html(light: &str, dark: &str) -> Result<String, String> {
+    for theme in [light, dark] {
+        if !PRISM_THEMES.contains(&theme) {
+            return Err(format!(
+                "unknown Prism theme `{theme}` (available: {})",
+                PRISM_THEMES.join(", ")
+            ));
+        }
+    }
+    let themes = color_scheme_stylesheets_html(
+        &format!("{PRISM_CDN}/themes/{light}.min.css"),
+        &format!("{PRISM_CDN}/themes/{dark}.min.css"),
+    );
+    let files: String = PRISM_FILES
+        .iter()
+        .map(|path| {
+            if path.ends_with(".css") {
+                format!("\n  <link rel=\"stylesheet\" href=\"{PRISM_CDN}/{path}\">")
+            } else {
+                format!("\n  <script src=\"{PRISM_CDN}/{path}\"></script>")
+            }
+        })
+        .collect();
+    Ok(format!(
+        "{themes}{files}\n  <script>{script}</script>",
+        script = prism_init_script()
+    ))
+}
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// Names of the built-in colour themes, e.g. `base16-ocean.dark` or `InspiredGitHub`.
+pub fn theme_names() -> Vec<String> {
+    ThemeSet::load_defaults().themes.into_keys().collect()
+}
+
+/// Returns the stylesheet that colours code highlighted at compile time with `theme`.
+pub fn highlight_css(theme: &str) -> Result<String, String> {
+    let themes = ThemeSet::load_defaults();
+    let theme = themes.themes.get(theme).ok_or_else(|| {
+        format!(
+            "unknown highlight theme `{theme}` (available: {})",
+            theme_names().join(", ")
+        )
+    })?;
+    css_for_theme_with_class_style(theme, CLASS_STYLE).map_err(|e| e.to_string())
+}
+
+/// Highlights `code` as `lang` (a language name or file extension) into HTML spans.
+///
+/// Returns `None` if the language is unknown.
+fn highlight_spans(code: &str, lang: &str) -> Option<String> {
+    let syntaxes = syntax_set();
+    let syntax = syntaxes.find_syntax_by_token(lang)?;
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, syntaxes, CLASS_STYLE);
+    for line in LinesWithEndings::from(code) {
+        generator
+            .parse_html_for_line_which_includes_newline(line)
+            .ok()?;
+    }
+    Some(generator.finalize())
+}
+
+/// Renders code as highlighted HTML spans. Code in an unknown language is only escaped.
+pub(crate) fn highlighted_code_html(code: &str, lang: &str) -> String {
+    (!lang.is_empty())
+        .then(|| highlight_spans(code, lang))
+        .flatten()
+        .unwrap_or_else(|| escape_html(code))
+}
+
+/// Splits highlighted HTML into lines, closing the spans that are open at the end of a
+/// line and reopening them on the next, so that each line is well-formed on its own.
+pub(crate) fn split_highlighted_lines(html: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut open: Vec<&str> = Vec::new();
+    let mut line = String::new();
+    let mut has_text = false;
+    let mut rest = html;
+
+    while let Some(pos) = rest.find(['<', '\n']) {
+        line.push_str(&rest[..pos]);
+        has_text |= pos > 0;
+        rest = &rest[pos..];
+        if rest.starts_with('\n') {
+            line.push_str(&"</span>".repeat(open.len()));
+            lines.push(std::mem::replace(&mut line, open.concat()));
+            has_text = false;
+            rest = &rest[1..];
+        } else {
+            let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+            let tag = &rest[..end];
+            if tag.starts_with("</") {
+                open.pop();
+            } else {
+                open.push(tag);
+            }
+            line.push_str(tag);
+            rest = &rest[end..];
+        }
+    }
+    line.push_str(rest);
+    // Code usually ends with a newline, leaving only reopened spans after it
+    if has_text || !rest.is_empty() {
+        line.push_str(&"</span>".repeat(open.len()));
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlighted_code_html() {
+        let html = highlighted_code_html("fn main() {}\n", "rust");
+
+        assert!(html.starts_with("<span class=\"hl-source hl-rust\">"));
+        assert!(html.contains("<span class=\"hl-storage hl-type hl-function hl-rust\">fn</span>"));
+
+        assert_eq!(
+            highlighted_code_html("<b>\n", "no-such-language"),
+            "&lt;b&gt;\n"
+        );
+    }
+
+    #[test]
+    fn test_split_highlighted_lines() {
+        let html = "<span class=\"a\">x\n<span class=\"b\">y\nz</span>\n</span>w";
+        assert_eq!(
+            split_highlighted_lines(html),
+            [
+                "<span class=\"a\">x</span>",
+                "<span class=\"a\"><span class=\"b\">y</span></span>",
+                "<span class=\"a\"><span class=\"b\">z</span></span>",
+                "<span class=\"a\"></span>w",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prism_head_html() {
+        let html = prism_head_html("prism", "prism-okaidia").unwrap();
+        assert!(html.contains("/themes/prism.min.css\" media=\"(prefers-color-scheme: light)\""));
+        assert!(
+            html.contains("/themes/prism-okaidia.min.css\" media=\"(prefers-color-scheme: dark)\"")
+        );
+
+        let err = prism_head_html("prism", "dracula").unwrap_err();
+        assert!(err.starts_with("unknown Prism theme `dracula`"), "{err}");
+    }
+
+    #[test]
+    fn test_prism_integrities_cover_only_known_files() {
+        let paths = prism_paths();
+        for (path, integrity) in PRISM_INTEGRITY {
+            assert!(paths.iter().any(|p| p == path), "{path}");
+            assert!(integrity.starts_with("sha384-"), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_prism_integrities_cover_every_file() {
+        let missing: Vec<String> = prism_paths()
+            .into_iter()
+            .filter(|path| !PRISM_INTEGRITY.iter().any(|(p, _)| p == path))
+            .collect();
+        assert!(
+            missing.is_empty(),
+            "no pinned integrity hash for {missing:?}; run the ignored \
+             `test_prism_integrity_is_pinned` to print the table"
+        );
+    }
+
+    /// Downloads the pinned Prism files and checks them against [`PRISM_INTEGRITY`],
+    /// printing the table to paste in if they differ.
+    #[test]
+    #[ignore = "needs network access"]
+    fn test_prism_integrity_is_pinned() {
+        let table: Vec<(String, String)> = prism_paths()
+            .into_iter()
+            .map(|path| {
+                let output = std::process::Command::new("curl")
+                    .args(["--fail", "--silent", "--location"])
+                    .arg(format!("{PRISM_CDN}/{path}"))
+                    .output()
+                    .unwrap();
+                assert!(output.status.success(), "could not download {path}");
+                let integrity = crate::security::integrity(&output.stdout);
+                (path, integrity)
+            })
+            .collect();
+        let pinned: Vec<(String, String)> = PRISM_INTEGRITY
+            .iter()
+            .map(|(path, integrity)| (path.to_string(), integrity.to_string()))
+            .collect();
+        if pinned != table {
+            let lines: Vec<String> = table
+                .iter()
+                .map(|(path, integrity)| format!("    (\"{path}\", \"{integrity}\"),"))
+                .collect();
+            panic!(
+                "PRISM_INTEGRITY does not match the CDN, it should be:\n{}",
+                lines.join("\n")
+            );
+        }
+    }
+
+    #[test]
+    fn test_highlight_css() {
+        let css = highlight_css("base16-ocean.dark").unwrap();
+        assert!(css.contains(".hl-code {"));
+
+        let err = highlight_css("missing").unwrap_err();
+        assert!(err.contains("InspiredGitHub"), "{err}");
+    }
+}
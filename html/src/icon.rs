@@ -1,5 +1,7 @@
 use std::path::Path;
 
+use crate::escape::escape_html;
+
 pub fn icon_html(icon_path: &str) -> String {
     let icon_type = Path::new(icon_path)
         .extension()
@@ -13,5 +15,8 @@ pub fn icon_html(icon_path: &str) -> String {
         })
         .unwrap_or("image/png");
 
-    format!(r#"<link rel="icon" href="{icon_path}" type="{icon_type}">"#)
+    format!(
+        r#"<link rel="icon" href="{icon_path}" type="{icon_type}">"#,
+        icon_path = escape_html(icon_path)
+    )
 }
@@ -0,0 +1,287 @@
+use std::path::{Path, PathBuf};
+
+use crate::diagnostic::Diagnostic;
+use crate::directive::{IncludeContext, missing_include_html, preprocess_directives, read_include};
+
+/// Maximum nesting depth of `@include[...]` directives.
+pub const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Expands a single `@include[path.md]` or `@include[path.md#section]` directive.
+///
+/// The included file is resolved relative to the including file, its front matter is
+/// dropped, and its own directives are expanded relative to its location. With
+/// `#section`, only the content under the heading whose text or slug matches `section`
+/// is spliced in, up to the next heading of the same or a higher level.
+///
+/// Include cycles and nesting deeper than [`MAX_INCLUDE_DEPTH`] are reported as errors.
+pub(crate) fn expand_markdown_include(
+    arg: &str,
+    line: usize,
+    ctx: &IncludeContext,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> String {
+    let (file_path, section) = match arg.split_once('#') {
+        Some((file_path, section)) => (file_path.trim(), Some(section.trim())),
+        None => (arg.trim(), None),
+    };
+
+    let Some((full_path, content)) = read_include("include", file_path, line, ctx, diagnostics)
+    else {
+        return missing_include_html("include", arg);
+    };
+
+    let current = ctx
+        .path
+        .canonicalize()
+        .unwrap_or_else(|_| ctx.path.to_path_buf());
+    let mut chain: Vec<PathBuf> = ctx.parents.to_vec();
+    chain.push(current);
+
+    if chain.contains(&full_path) {
+        let cycle: Vec<String> = chain
+            .iter()
+            .skip_while(|path| **path != full_path)
+            .chain(std::iter::once(&full_path))
+            .map(|path| file_name(path))
+            .collect();
+        diagnostics.push(Diagnostic::error(
+            ctx.path,
+            line,
+            format!("`@include` cycle: {}", cycle.join(" -> ")),
+        ));
+        return String::new();
+    }
+
+    if ctx.parents.len() >= MAX_INCLUDE_DEPTH {
+        diagnostics.push(Diagnostic::error(
+            ctx.path,
+            line,
+            format!("`@include[{arg}]` exceeds the maximum include depth of {MAX_INCLUDE_DEPTH}"),
+        ));
+        return String::new();
+    }
+
+    let (body, body_offset) = strip_front_matter(&content);
+    let (body, line_offset) = match section {
+        Some(section) => match find_section(body, section) {
+            Some((section_body, section_offset)) => (section_body, body_offset + section_offset),
+            None => {
+                diagnostics.push(Diagnostic::warning(
+                    ctx.path,
+                    line,
+                    format!("`@include` section `{section}` not found in {file_path}"),
+                ));
+                return missing_include_html("include", arg);
+            }
+        },
+        None => (body, body_offset),
+    };
+
+    let child = IncludeContext {
+        path: &full_path,
+        line_offset,
+        options: ctx.options,
+        parents: &chain,
+    };
+    let expanded = preprocess_directives(body, &child, diagnostics);
+    expanded.trim_end_matches('\n').to_string()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+/// Drops a leading `---` YAML block, returning the rest and the number of lines removed.
+fn strip_front_matter(content: &str) -> (&str, usize) {
+    let mut lines = content.split_inclusive('\n');
+    match lines.next() {
+        Some(first) if first.trim() == "---" => {
+            let mut consumed = first.len();
+            for (i, line) in lines.enumerate() {
+                consumed += line.len();
+                if line.trim() == "---" {
+                    return (&content[consumed..], i + 2);
+                }
+            }
+            (content, 0)
+        }
+        _ => (content, 0),
+    }
+}
+
+/// Parses an ATX heading (`## Title`), returning its level and text.
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let trimmed = line.trim_end();
+    let level = trimmed.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &trimmed[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((level, rest.trim().trim_end_matches('#').trim()))
+}
+
+/// Turns heading text into the slug used to name a section, e.g. `Setup Steps` -> `setup-steps`.
+pub(crate) fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for c in text.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() || c == '_' || c == '-' {
+            slug.push(c);
+        } else if c.is_whitespace() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_matches('-').to_string()
+}
+
+/// Finds the section under the heading named `section`, skipping fenced code blocks.
+///
+/// Returns the section body and the number of lines that precede it in `markdown`.
+fn find_section<'a>(markdown: &'a str, section: &str) -> Option<(&'a str, usize)> {
+    let mut in_fence = false;
+    let mut start: Option<(usize, usize, usize)> = None; // (level, byte offset, line offset)
+    let mut pos = 0;
+
+    for (i, line) in markdown.split_inclusive('\n').enumerate() {
+        let line_start = pos;
+        pos += line.len();
+
+        if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let Some((level, text)) = parse_heading(line) else {
+            continue;
+        };
+
+        match start {
+            Some((start_level, start_pos, start_line)) if level <= start_level => {
+                return Some((&markdown[start_pos..line_start], start_line));
+            }
+            None if text == section || slugify(text) == section => {
+                start = Some((level, pos, i + 1));
+            }
+            _ => {}
+        }
+    }
+
+    start.map(|(_, start_pos, start_line)| (&markdown[start_pos..], start_line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::preprocess;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_include_splices_partial_and_resolves_its_code_relative_to_it() {
+        let temp_dir = TempDir::new().unwrap();
+        let partials = temp_dir.path().join("partials");
+        fs::create_dir(&partials).unwrap();
+        fs::write(
+            partials.join("setup.md"),
+            "---\ntitle: x\n---\nInstall first:\n\n@code[install.sh]\n",
+        )
+        .unwrap();
+        fs::write(partials.join("install.sh"), "cargo install mejiro-cli").unwrap();
+
+        let (result, diagnostics) = preprocess(
+            "# Post\n\n@include[partials/setup.md]\n",
+            temp_dir.path(),
+            &Default::default(),
+        );
+
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert!(!result.contains("title: x"));
+        assert!(result.contains("Install first:"));
+        assert!(result.contains("```bash title=\"install.sh\"\ncargo install mejiro-cli\n```"));
+    }
+
+    #[test]
+    fn test_include_named_section() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("other.md"),
+            "# Other\n\n## Disclaimer\n\nNot financial advice.\n\n### Details\n\nMore.\n\n## Next\n\nSkipped.\n",
+        )
+        .unwrap();
+
+        let (result, diagnostics) = preprocess(
+            "@include[other.md#disclaimer]",
+            temp_dir.path(),
+            &Default::default(),
+        );
+
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert!(result.contains("Not financial advice."));
+        assert!(result.contains("### Details"));
+        assert!(!result.contains("## Disclaimer"));
+        assert!(!result.contains("Skipped."));
+    }
+
+    #[test]
+    fn test_include_reports_missing_section() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("other.md"), "# Other\n").unwrap();
+
+        let (result, diagnostics) = preprocess(
+            "@include[other.md#nope]",
+            temp_dir.path(),
+            &Default::default(),
+        );
+
+        assert!(result.contains("include-missing"));
+        assert_eq!(diagnostics.len(), 1);
+        assert!(!diagnostics[0].is_error());
+    }
+
+    #[test]
+    fn test_include_detects_cycles() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("a.md"), "A\n@include[b.md]\n").unwrap();
+        fs::write(temp_dir.path().join("b.md"), "B\n@include[a.md]\n").unwrap();
+
+        let (_, diagnostics) = preprocess("@include[a.md]", temp_dir.path(), &Default::default());
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+        assert!(diagnostics[0].message.contains("a.md -> b.md -> a.md"));
+        assert_eq!(diagnostics[0].path.file_name().unwrap(), "b.md");
+        assert_eq!(diagnostics[0].line, 2);
+    }
+
+    #[test]
+    fn test_include_enforces_depth_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        for i in 0..=MAX_INCLUDE_DEPTH + 1 {
+            let next = i + 1;
+            fs::write(
+                temp_dir.path().join(format!("p{i}.md")),
+                format!("@include[p{next}.md]"),
+            )
+            .unwrap();
+        }
+
+        let (_, diagnostics) = preprocess("@include[p0.md]", temp_dir.path(), &Default::default());
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("maximum include depth"));
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Setup Steps"), "setup-steps");
+        assert_eq!(slugify("  What's new?  "), "whats-new");
+        assert_eq!(slugify("環境 構築"), "環境-構築");
+    }
+}
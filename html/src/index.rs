@@ -1,11 +1,15 @@
+use crate::escape::escape_html;
 use crate::metadata::Post;
+use crate::svg_icon::Icon;
 
+/// Renders the index page. `head_html` is extra markup for `<head>`, such as the icon
+/// link and the code highlighting assets.
 pub fn index_html(
     owner_name: &str,
     posts: &[Post],
     aside_html: &str,
     footer_html: &str,
-    icon_html: &str,
+    head_html: &str,
     csv_file_path: &str,
 ) -> String {
     // Start the page with the container, aside_html, and main
@@ -17,52 +21,49 @@ pub fn index_html(
   <meta name="viewport" content="width=device-width, initial-scale=1.0" />
   <title>{owner_name} Blog</title>
   <link rel="stylesheet" href="{csv_file_path}">
-  <link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/prismjs@1.29.0/themes/prism-tomorrow.min.css">
-  <script src="https://cdn.jsdelivr.net/npm/prismjs@1.29.0/components/prism-core.min.js"></script>
-  <script src="https://cdn.jsdelivr.net/npm/prismjs@1.29.0/plugins/autoloader/prism-autoloader.min.js"></script>
-  <script>
-    // Load languages on demand from the CDN
-    if (window.Prism && Prism.plugins.autoloader) {{
-      Prism.plugins.autoloader.languages_path = "https://cdn.jsdelivr.net/npm/prismjs@1.29.0/components/";
-    }}
-  </script>
-  {icon_html}
+  {head_html}
 </head>
 <body>
   <div class="container">
     {aside_html}
     <main>
       <div class="search-bar-wrapper">
-        <i id="search-trigger" class="fas fa-search"></i>
+        <button type="button" id="search-trigger" class="icon-button" aria-label="Search">{search_icon}</button>
         <span id="search-wrapper">
           <input type="text" id="search-input" placeholder="Search..." />
-          <i id="search-cleaner" class="fa fa-times"></i>
+          <button type="button" id="search-cleaner" class="icon-button" aria-label="Clear search">{close_icon}</button>
         </span>
       </div>
 
       <h1>Posts</h1>
       <ul id="post-list">
-"#
+"#,
+        owner_name = escape_html(owner_name),
+        csv_file_path = escape_html(csv_file_path),
+        search_icon = Icon::Search.svg(),
+        close_icon = Icon::Close.svg(),
     );
 
     // Loop through the posts and build the list
     for post in posts {
-        let summary_html = post
-            .meta
-            .tldr
-            .as_ref()
-            .map_or(String::new(), |s| format!(r#"<p class="summary">{s}</p>"#));
+        let summary_html = post.meta.tldr.as_ref().map_or(String::new(), |s| {
+            format!(r#"<p class="summary">{}</p>"#, escape_html(s))
+        });
 
         let topics_html = if !post.meta.topics.is_empty() {
-            let topics = post.meta.topics.join(", ");
-            format!(r#"<p class="topics">Tags: {topics}</p>"#)
+            let topics = escape_html(&post.meta.topics.join(", "));
+            format!(
+                r#"<p class="topics">{tag_icon} Tags: {topics}</p>"#,
+                tag_icon = Icon::Tag.svg()
+            )
         } else {
             String::new()
         };
 
         let date_html = format!(
-            r#"<p class="published-at">Published at: {published_at}</p>"#,
-            published_at = post.meta.published_at
+            r#"<p class="published-at">{calendar_icon} Published at: {published_at}</p>"#,
+            calendar_icon = Icon::Calendar.svg(),
+            published_at = escape_html(&post.meta.published_at)
         );
 
         index_html.push_str(&format!(
@@ -73,8 +74,8 @@ pub fn index_html(
           {date_html}
         </li>
 "#,
-            name = post.name,
-            title = post.meta.title,
+            name = escape_html(&post.name),
+            title = escape_html(&post.meta.title),
             summary_html = summary_html,
             topics_html = topics_html,
             date_html = date_html
@@ -94,21 +95,52 @@ pub fn index_html(
     index_html.push_str(footer_html);
 
     // Add the <script> block for search logic
-    index_html.push_str(
-        r#"
-  <script type="module">
+    index_html.push_str(&format!(
+        "\n  <script type=\"module\">{script}</script>\n",
+        script = search_script()
+    ));
+
+    // Close body and html
+    index_html.push_str("\n</body>\n</html>\n");
+
+    index_html
+}
+
+/// The module script of the index page that loads the search index and filters the
+/// post list as the reader types.
+pub(crate) fn search_script() -> String {
+    r#"
     import init, { search } from './mejiro-search-pkg/mejiro_search.js';
 
+    const tagIcon = '{tag_icon}';
+    const calendarIcon = '{calendar_icon}';
+
     let postsData = [];
 
     async function loadPosts() {
-      const response = await fetch('/posts.json');
+      const response = await fetch('posts.json');
       postsData = await response.json();
     }
 
     async function initialize() {
       await init();
       await loadPosts();
+
+      // Searches linked from elsewhere, such as a search engine's site search box
+      const query = new URLSearchParams(location.search).get('q');
+      if (query) {
+        searchInput.value = query;
+        doSearch();
+      }
+    }
+
+    function paragraph(className, icon, text) {
+      const p = document.createElement('p');
+      p.className = className;
+      // The icons are constant SVG markup from the page itself
+      p.insertAdjacentHTML('afterbegin', icon);
+      p.append(icon ? ` ${text}` : text);
+      return p;
     }
 
     function doSearch() {
@@ -125,18 +157,26 @@ pub fn index_html(
       const results = search(postsData, query);
       const plainResults = results.map(item => Object.fromEntries(item));
 
+      searchResults.replaceChildren();
       if (plainResults.length === 0) {
-        searchResults.innerHTML = '<li>No posts found.</li>';
+        const li = document.createElement('li');
+        li.textContent = 'No posts found.';
+        searchResults.appendChild(li);
       } else {
-        searchResults.innerHTML = '';
         plainResults.forEach(post => {
+          // Post fields are inserted as text, never parsed as HTML
           const li = document.createElement('li');
-          li.innerHTML = `
-            <a href="${post.path}"><strong>${post.title}</strong></a>
-            <p class="summary">${post.tldr || ''}</p>
-            <p class="topics">Tags: ${post.tags.join(', ')}</p>
-            <p class="published-at">Published at: ${post.published_at || 'Unknown'}</p>
-          `;
+          const link = document.createElement('a');
+          link.href = post.path;
+          const title = document.createElement('strong');
+          title.textContent = post.title;
+          link.appendChild(title);
+          li.append(
+            link,
+            paragraph('summary', '', post.tldr || ''),
+            paragraph('topics', tagIcon, `Tags: ${post.tags.join(', ')}`),
+            paragraph('published-at', calendarIcon, `Published at: ${post.published_at || 'Unknown'}`),
+          );
           searchResults.appendChild(li);
         });
       }
@@ -165,13 +205,36 @@ pub fn index_html(
     searchInput.addEventListener('input', doSearch);
 
     initialize();
-    Prism.highlightAll();
-  </script>
-"#,
-    );
+  "#
+    .replace("{tag_icon}", &Icon::Tag.svg())
+    .replace("{calendar_icon}", &Icon::Calendar.svg())
+}
 
-    // Close body and html
-    index_html.push_str("\n</body>\n</html>\n");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::BlogMeta;
+
+    #[test]
+    fn test_index_html_escapes_metadata() {
+        let front_matter = "---\ntitle: '<img src=x onerror=alert(1)>'\ntopics: ['a&b']\npublished: true\npublished_at: 2024-01-01\ntldr: '</p><p>'\n---\n";
+        let (meta, _) = BlogMeta::from_markdown_str(front_matter).unwrap();
+        let post = Post {
+            meta,
+            html_body: String::new(),
+            markdown_body: String::new(),
+            name: "a\"b".to_string(),
+            diagnostics: Vec::new(),
+            links: Vec::new(),
+        };
 
-    index_html
+        let html = index_html("Owner <x>", &[post], "", "", "", "style.css");
+
+        assert!(html.contains("<title>Owner &lt;x&gt; Blog</title>"));
+        assert!(html.contains(r#"<a href="posts/a&quot;b.html"><strong>&lt;img src=x onerror=alert(1)&gt;</strong></a>"#));
+        assert!(html.contains(r#"<p class="summary">&lt;/p&gt;&lt;p&gt;</p>"#));
+        assert!(html.contains("Tags: a&amp;b</p>"));
+        // Search results are built from text nodes, not HTML
+        assert!(!html.contains(".innerHTML"));
+    }
 }
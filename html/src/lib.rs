@@ -1,12 +1,42 @@
 mod aside;
+pub mod bibliography;
 pub mod code_block;
+pub mod code_fence;
+pub mod code_tabs;
+pub mod color_scheme;
+pub mod diagnostic;
+mod diff;
+pub mod directive;
+pub mod escape;
+mod figure;
 mod footer;
+mod git;
+pub mod highlight;
 mod icon;
+mod include;
 mod index;
 pub mod metadata;
+mod notebook;
+pub mod og_card;
+pub mod options;
 mod post;
+mod prose;
+pub mod raw_html;
+mod render;
+pub mod ruby;
+mod run;
+pub mod security;
+mod social;
+pub mod structured_data;
+pub mod svg_icon;
+mod table;
+#[cfg(test)]
+mod test_fixtures;
+pub mod wiki_link;
 pub use aside::aside_html;
 pub use footer::footer_html;
 pub use icon::icon_html;
 pub use index::index_html;
 pub use post::post_html;
+pub use social::{index_social_html, post_social_html};
+pub use wiki_link::backlinks_html;
@@ -1,9 +1,16 @@
-use pulldown_cmark::{Parser, html};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 
-use crate::code_block::preprocess_code_includes;
+use crate::bibliography::{load_bibliography, render_citations};
+use crate::code_tabs::group_code_tabs;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::directive::{IncludeContext, preprocess_directives};
+use crate::figure::number_figures;
+use crate::options::{CitationStyle, FootnoteStyle, RenderOptions};
+use crate::render::render_markdown;
+use crate::ruby::render_ruby;
+use crate::wiki_link::resolve_wiki_links;
 
 /// Possible errors when parsing a blog post from a Markdown file.
 #[derive(Debug)]
@@ -11,6 +18,8 @@ pub enum BlogParseError {
     MetadataNotFound,
     YamlParseError(String),
     IoError(String),
+    /// One or more build diagnostics were errors (or warnings in strict mode).
+    Diagnostics(Vec<Diagnostic>),
 }
 
 impl std::fmt::Display for BlogParseError {
@@ -21,6 +30,10 @@ impl std::fmt::Display for BlogParseError {
             }
             BlogParseError::YamlParseError(msg) => write!(f, "Failed to parse metadata: {msg}"),
             BlogParseError::IoError(msg) => write!(f, "File error: {msg}"),
+            BlogParseError::Diagnostics(diagnostics) => {
+                let lines: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+                write!(f, "{}", lines.join("\n"))
+            }
         }
     }
 }
@@ -35,6 +48,21 @@ pub struct BlogMeta {
     pub published: bool,
     pub published_at: String,
     pub tldr: Option<String>,
+    /// Overrides the site-wide footnote style (`end` or `sidenotes`) for this post.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footnotes: Option<FootnoteStyle>,
+    /// BibTeX file for `@cite[...]`, relative to the post. Overrides the site-wide one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bibliography: Option<String>,
+    /// Overrides the site-wide citation style (`numeric` or `author-year`) for this post.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub citations: Option<CitationStyle>,
+    /// Image shown when the post is shared, relative to the post or a full URL.
+    #[serde(default, alias = "cover", skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// URL of the original article, for posts published elsewhere first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonical_url: Option<String>,
 }
 
 impl BlogMeta {
@@ -79,14 +107,23 @@ pub struct Post {
     pub html_body: String,
     pub markdown_body: String,
     pub name: String, // file name without extension
+    /// Warnings found while building the post.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Names of the posts this post links to with `[[post-name]]`.
+    pub links: Vec<String>,
 }
 
 impl Post {
     /// Parses a Markdown file into a Post.
     /// - Returns `Err(BlogParseError)` if the file cannot be read or metadata is invalid.
     /// - Returns `Ok(None)` if the post is not published.
+    /// - Returns `Err(BlogParseError::Diagnostics)` if building the post reported errors
+    ///   (in strict mode, warnings count as errors).
     /// - Returns `Ok(Some(Post))` if successfully parsed and published.
-    pub fn from_markdown_file(path: &Path) -> Result<Option<Self>, BlogParseError> {
+    pub fn from_markdown_file(
+        path: &Path,
+        options: &RenderOptions,
+    ) -> Result<Option<Self>, BlogParseError> {
         let content =
             fs::read_to_string(path).map_err(|e| BlogParseError::IoError(e.to_string()))?;
 
@@ -96,26 +133,174 @@ impl Post {
             return Ok(None);
         }
 
-        // Preprocess the markdown body to replace @code[...] directives with actual code blocks
-        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
-        let processed_body = preprocess_code_includes(&body_md, base_dir);
+        // Preprocess the markdown body to expand @code[...] and @include[...] directives
+        let ctx = IncludeContext {
+            path,
+            line_offset: front_matter_line_count(&content),
+            options,
+            parents: &[],
+        };
+        let mut diagnostics = Vec::new();
+        let processed_body = preprocess_directives(&body_md, &ctx, &mut diagnostics);
+
+        let processed_body = group_code_tabs(&processed_body);
 
-        let parser = Parser::new(&processed_body);
-        let mut html_output = String::new();
-        html::push_html(&mut html_output, parser);
+        let (processed_body, unknown_refs) = number_figures(&processed_body);
+        for id in unknown_refs {
+            let line =
+                line_of_text(&content, &format!("@ref[{id}]")).unwrap_or(ctx.line_offset + 1);
+            diagnostics.push(Diagnostic::warning(
+                path,
+                line,
+                format!("`@ref[{id}]` does not match any figure"),
+            ));
+        }
+
+        let (processed_body, links, unresolved) =
+            resolve_wiki_links(&processed_body, &options.post_titles);
+        for target in unresolved {
+            let line =
+                line_of_text(&content, &format!("[[{target}")).unwrap_or(ctx.line_offset + 1);
+            diagnostics.push(Diagnostic::warning(
+                path,
+                line,
+                format!("`[[{target}]]` does not match any published post"),
+            ));
+        }
+
+        let processed_body = render_ruby(&processed_body);
+
+        let processed_body = if processed_body.contains("@cite[") {
+            self::cite(
+                path,
+                &content,
+                &meta,
+                options,
+                &processed_body,
+                &mut diagnostics,
+            )
+        } else {
+            processed_body
+        };
+
+        let footnotes = meta.footnotes.unwrap_or(options.footnotes);
+        let (html_output, changes) = render_markdown(
+            &processed_body,
+            footnotes,
+            options.highlighting,
+            options.raw_html,
+        );
+        for change in changes {
+            // HTML from an include is reported at the start of the post
+            let snippet = processed_body[change.pos..]
+                .lines()
+                .next()
+                .unwrap_or_default();
+            let line = line_of_text(&content, snippet).unwrap_or(ctx.line_offset + 1);
+            diagnostics.push(Diagnostic::warning(path, line, change.message));
+        }
+
+        if options.strict {
+            for diagnostic in &mut diagnostics {
+                diagnostic.severity = Severity::Error;
+            }
+        }
+        if diagnostics.iter().any(Diagnostic::is_error) {
+            return Err(BlogParseError::Diagnostics(diagnostics));
+        }
 
         Ok(Some(Post {
             meta,
             html_body: html_output,
             markdown_body: content,
             name: path.file_stem().unwrap().to_string_lossy().to_string(),
+            diagnostics,
+            links,
         }))
     }
 }
 
+/// Renders the `@cite[...]` citations of a post against its bibliography, reporting a
+/// missing bibliography or unknown keys.
+fn cite(
+    path: &Path,
+    content: &str,
+    meta: &BlogMeta,
+    options: &RenderOptions,
+    markdown: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> String {
+    let first_cite = line_of_text(content, "@cite[").unwrap_or(1);
+    let bibliography_path = match &meta.bibliography {
+        Some(file) => path.parent().unwrap_or_else(|| Path::new(".")).join(file),
+        None => match &options.bibliography {
+            Some(file) => file.clone(),
+            None => {
+                diagnostics.push(Diagnostic::warning(
+                    path,
+                    first_cite,
+                    "`@cite` needs a bibliography; set `bibliography` in the front matter or mejiro.yml",
+                ));
+                return markdown.to_string();
+            }
+        },
+    };
+
+    let bibliography = match load_bibliography(&bibliography_path) {
+        Ok(bibliography) => bibliography,
+        Err(e) => {
+            let line = match meta.bibliography {
+                Some(_) => line_of_text(content, "bibliography:").unwrap_or(1),
+                None => first_cite,
+            };
+            diagnostics.push(Diagnostic::warning(
+                path,
+                line,
+                format!("could not load bibliography {e}"),
+            ));
+            return markdown.to_string();
+        }
+    };
+
+    let style = meta.citations.unwrap_or(options.citations);
+    let (markdown, unknown) = render_citations(markdown, &bibliography, style);
+    for key in unknown {
+        let line = content
+            .lines()
+            .position(|line| line.contains("@cite[") && line.contains(key.as_str()))
+            .map_or(first_cite, |i| i + 1);
+        diagnostics.push(Diagnostic::warning(
+            path,
+            line,
+            format!("`@cite` key `{key}` is not in the bibliography"),
+        ));
+    }
+    markdown
+}
+
+/// Returns the 1-based line of the first occurrence of `needle` in `content`.
+fn line_of_text(content: &str, needle: &str) -> Option<usize> {
+    content
+        .find(needle)
+        .map(|pos| content[..pos].matches('\n').count() + 1)
+}
+
+/// Returns the number of lines taken up by the YAML front matter, including both `---` fences.
+fn front_matter_line_count(content: &str) -> usize {
+    content
+        .lines()
+        .enumerate()
+        .skip(1)
+        .find(|(_, line)| line.trim() == "---")
+        .map_or(0, |(i, _)| i + 1)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::options::RawHtml;
+    use std::fs;
+    use tempfile::TempDir;
 
     #[test]
     fn test_from_markdown_str_invalid_yaml() {
@@ -155,4 +340,70 @@ This post does not have any metadata.
         let result = BlogMeta::from_markdown_str(markdown);
         assert!(matches!(result, Err(BlogParseError::MetadataNotFound)));
     }
+
+    #[test]
+    fn test_from_markdown_file_sanitizes_included_html() {
+        let temp_dir = TempDir::new().unwrap();
+        let notebook = r#"{"cells": [{"cell_type": "markdown", "source": "<script>alert(2)</script><img src=y onerror=alert(3)>"}], "metadata": {}}"#;
+        fs::write(temp_dir.path().join("n.ipynb"), notebook).unwrap();
+        fs::write(
+            temp_dir.path().join("c.txt"),
+            "```\n<img src=x onerror=alert(1)>\n",
+        )
+        .unwrap();
+        let path = temp_dir.path().join("post.md");
+        fs::write(
+            &path,
+            "---\ntitle: T\ntopics: []\npublished: true\npublished_at: 2024-01-02\ntldr: null\n---\n\n@notebook[n.ipynb]\n\n@code[c.txt]\n",
+        )
+        .unwrap();
+        let options = RenderOptions {
+            raw_html: RawHtml::Sanitize,
+            ..Default::default()
+        };
+
+        let post = Post::from_markdown_file(&path, &options).unwrap().unwrap();
+
+        assert!(!post.html_body.contains("<script"));
+        assert!(!post.html_body.contains("onerror=alert(3)"));
+        assert!(!post.html_body.contains("<img src=x"));
+        assert!(post.html_body.contains("<div class=\"notebook\">"));
+        assert!(
+            post.html_body
+                .contains("&lt;img src=x onerror=alert(1)&gt;")
+        );
+        assert!(!post.html_body.contains("data-mejiro"));
+        assert_eq!(post.diagnostics.len(), 2);
+        assert!(post.diagnostics.iter().all(|d| d.line == 8));
+    }
+
+    #[test]
+    fn test_from_markdown_file_strict_fails_on_warnings() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("post.md");
+        fs::write(
+            &path,
+            "---\ntitle: T\ntopics: []\npublished: true\npublished_at: 2024-01-02\ntldr: null\n---\n\n@code[missing.py]\n",
+        )
+        .unwrap();
+
+        let post = Post::from_markdown_file(&path, &RenderOptions::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(post.diagnostics.len(), 1);
+        assert!(!post.diagnostics[0].is_error());
+
+        let options = RenderOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let Err(BlogParseError::Diagnostics(diagnostics)) =
+            Post::from_markdown_file(&path, &options)
+        else {
+            panic!("Expected Diagnostics");
+        };
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].is_error());
+        assert_eq!(diagnostics[0].line, 9);
+    }
 }
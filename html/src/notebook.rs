@@ -0,0 +1,302 @@
+use serde::Deserialize;
+
+use crate::code_fence::fence_for;
+use crate::raw_html::generated;
+
+/// A Jupyter notebook (nbformat 4), reduced to the parts needed for rendering.
+#[derive(Deserialize, Debug)]
+pub struct Notebook {
+    pub cells: Vec<Cell>,
+    #[serde(default)]
+    pub metadata: NotebookMetadata,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct NotebookMetadata {
+    #[serde(default)]
+    pub language_info: Option<LanguageInfo>,
+    #[serde(default)]
+    pub kernelspec: Option<KernelSpec>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LanguageInfo {
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct KernelSpec {
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Cell {
+    pub cell_type: String,
+    #[serde(default)]
+    pub source: MultilineText,
+    #[serde(default)]
+    pub outputs: Vec<Output>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Output {
+    pub output_type: String,
+    #[serde(default)]
+    pub text: MultilineText,
+    #[serde(default)]
+    pub data: OutputData,
+    #[serde(default)]
+    pub ename: String,
+    #[serde(default)]
+    pub evalue: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct OutputData {
+    #[serde(rename = "text/plain", default)]
+    pub text_plain: Option<MultilineText>,
+    #[serde(rename = "image/png", default)]
+    pub image_png: Option<MultilineText>,
+}
+
+/// Notebook text fields are stored either as a single string or as a list of lines.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum MultilineText {
+    Text(String),
+    Lines(Vec<String>),
+}
+
+impl Default for MultilineText {
+    fn default() -> Self {
+        MultilineText::Text(String::new())
+    }
+}
+
+impl MultilineText {
+    pub fn as_string(&self) -> String {
+        match self {
+            MultilineText::Text(text) => text.clone(),
+            MultilineText::Lines(lines) => lines.concat(),
+        }
+    }
+}
+
+/// Options accepted by `@notebook[file.ipynb ...]`.
+#[derive(Debug, Default, PartialEq)]
+pub struct NotebookOptions {
+    pub hide_input: bool,
+    pub hide_output: bool,
+    /// 1-based, inclusive range of cells to render. `None` renders every cell.
+    pub cells: Option<(usize, usize)>,
+}
+
+impl NotebookOptions {
+    /// Parses space separated options such as `hide_input`, `hide_output` and `cells=2-5`.
+    pub fn parse(options: &[&str]) -> Result<Self, String> {
+        let mut parsed = NotebookOptions::default();
+        for option in options {
+            match *option {
+                "hide_input" => parsed.hide_input = true,
+                "hide_output" => parsed.hide_output = true,
+                _ => match option.strip_prefix("cells=") {
+                    Some(range) => parsed.cells = Some(parse_cell_range(range)?),
+                    None => return Err(format!("unknown option `{option}`")),
+                },
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Parses `3`, `3-7`, `3-` or `-7` into a 1-based inclusive range.
+fn parse_cell_range(range: &str) -> Result<(usize, usize), String> {
+    let invalid = || format!("invalid cell range `{range}`");
+    let parse = |s: &str, default: usize| -> Result<usize, String> {
+        if s.is_empty() {
+            Ok(default)
+        } else {
+            s.parse().map_err(|_| invalid())
+        }
+    };
+
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) => (parse(start, 1)?, parse(end, usize::MAX)?),
+        None => {
+            let cell = parse(range, 0)?;
+            (cell, cell)
+        }
+    };
+    if start == 0 || start > end {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
+
+impl Notebook {
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| e.to_string())
+    }
+
+    fn language(&self) -> &str {
+        self.metadata
+            .language_info
+            .as_ref()
+            .map(|info| info.name.as_str())
+            .or_else(|| {
+                self.metadata
+                    .kernelspec
+                    .as_ref()
+                    .and_then(|spec| spec.language.as_deref())
+            })
+            .unwrap_or("python")
+    }
+
+    /// Renders the notebook as markdown: markdown cells as-is, code cells as fenced code
+    /// blocks, text outputs as `text` blocks and PNG outputs as inline images.
+    pub fn to_markdown(&self, options: &NotebookOptions) -> String {
+        let (first, last) = options.cells.unwrap_or((1, usize::MAX));
+        let mut markdown = generated("<div class=\"notebook\">\n\n");
+
+        for cell in self
+            .cells
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| (first..=last).contains(&(i + 1)))
+            .map(|(_, cell)| cell)
+        {
+            match cell.cell_type.as_str() {
+                "markdown" => push_block(&mut markdown, &cell.source.as_string()),
+                "code" => {
+                    if !options.hide_input {
+                        push_fenced(&mut markdown, self.language(), &cell.source.as_string());
+                    }
+                    if !options.hide_output {
+                        for output in &cell.outputs {
+                            push_output(&mut markdown, output);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        markdown.push_str("</div>");
+        markdown
+    }
+}
+
+fn push_block(markdown: &mut String, block: &str) {
+    let block = block.trim_end();
+    if !block.is_empty() {
+        markdown.push_str(block);
+        markdown.push_str("\n\n");
+    }
+}
+
+fn push_fenced(markdown: &mut String, lang: &str, code: &str) {
+    let code = code.trim_end_matches('\n');
+    if code.is_empty() {
+        return;
+    }
+    let fence = fence_for(code);
+    markdown.push_str(&format!("{fence}{lang}\n{code}\n{fence}\n\n"));
+}
+
+fn push_output(markdown: &mut String, output: &Output) {
+    match output.output_type.as_str() {
+        "stream" => push_fenced(markdown, "text", &output.text.as_string()),
+        "execute_result" | "display_data" => {
+            if let Some(png) = &output.data.image_png {
+                let png: String = png
+                    .as_string()
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect();
+                push_block(markdown, &format!("![output](data:image/png;base64,{png})"));
+            } else if let Some(text) = &output.data.text_plain {
+                push_fenced(markdown, "text", &text.as_string());
+            }
+        }
+        "error" => push_fenced(
+            markdown,
+            "text",
+            &format!(
+                "{ename}: {evalue}",
+                ename = output.ename,
+                evalue = output.evalue
+            ),
+        ),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOTEBOOK: &str = r##"{
+      "metadata": {"language_info": {"name": "python"}},
+      "nbformat": 4,
+      "nbformat_minor": 5,
+      "cells": [
+        {"cell_type": "markdown", "metadata": {}, "source": ["# Analysis\n", "Intro text"]},
+        {"cell_type": "code", "metadata": {}, "execution_count": 1,
+         "source": "print('hi')",
+         "outputs": [{"output_type": "stream", "name": "stdout", "text": ["hi\n"]}]},
+        {"cell_type": "code", "metadata": {}, "execution_count": 2,
+         "source": ["1 + 1"],
+         "outputs": [{"output_type": "execute_result", "execution_count": 2, "metadata": {},
+                      "data": {"text/plain": ["2"]}}]},
+        {"cell_type": "code", "metadata": {}, "execution_count": 3,
+         "source": ["plot()"],
+         "outputs": [{"output_type": "display_data", "metadata": {},
+                      "data": {"image/png": "iVBORw0KGgo\nAAAA\n", "text/plain": ["<Figure>"]}}]}
+      ]
+    }"##;
+
+    #[test]
+    fn test_notebook_renders_cells_and_outputs() {
+        let notebook = Notebook::from_json(NOTEBOOK).unwrap();
+        let markdown = notebook.to_markdown(&NotebookOptions::default());
+
+        assert!(markdown.contains("# Analysis\nIntro text"));
+        assert!(markdown.contains("```python\nprint('hi')\n```"));
+        assert!(markdown.contains("```text\nhi\n```"));
+        assert!(markdown.contains("```text\n2\n```"));
+        assert!(markdown.contains("![output](data:image/png;base64,iVBORw0KGgoAAAA)"));
+        assert!(!markdown.contains("<Figure>"));
+    }
+
+    #[test]
+    fn test_notebook_hide_input_and_cell_range() {
+        let notebook = Notebook::from_json(NOTEBOOK).unwrap();
+        let options = NotebookOptions::parse(&["hide_input", "cells=2-3"]).unwrap();
+        let markdown = notebook.to_markdown(&options);
+
+        assert!(!markdown.contains("# Analysis"));
+        assert!(!markdown.contains("print('hi')"));
+        assert!(markdown.contains("```text\nhi\n```"));
+        assert!(markdown.contains("```text\n2\n```"));
+        assert!(!markdown.contains("image/png"));
+    }
+
+    #[test]
+    fn test_notebook_options_parse() {
+        assert_eq!(
+            NotebookOptions::parse(&["hide_output", "cells=4"]).unwrap(),
+            NotebookOptions {
+                hide_input: false,
+                hide_output: true,
+                cells: Some((4, 4)),
+            }
+        );
+        assert_eq!(
+            NotebookOptions::parse(&["cells=3-"]).unwrap().cells,
+            Some((3, usize::MAX))
+        );
+        assert!(NotebookOptions::parse(&["cells=5-2"]).is_err());
+        assert!(NotebookOptions::parse(&["verbose"]).is_err());
+    }
+}
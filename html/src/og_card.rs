@@ -0,0 +1,360 @@
+use ab_glyph::{Font, FontVec, OutlineCurve, Point, PxScale, ScaleFont};
+use tiny_skia::{
+    Color, FillRule, FilterQuality, Paint, PathBuilder, Pixmap, PixmapPaint, Rect, Transform,
+};
+
+/// Size of the card, as recommended for `og:image`.
+pub const CARD_WIDTH: u32 = 1200;
+pub const CARD_HEIGHT: u32 = 630;
+
+const PADDING: f32 = 80.0;
+const ICON_SIZE: f32 = 64.0;
+const SITE_TITLE_SIZE: f32 = 32.0;
+const TITLE_SIZE: f32 = 64.0;
+const TITLE_MAX_LINES: usize = 3;
+const FOOTER_SIZE: f32 = 28.0;
+
+// The light colour scheme of the default stylesheet
+const BACKGROUND: (u8, u8, u8) = (0xff, 0xfc, 0xf0);
+const HEADING: (u8, u8, u8) = (0x0f, 0x17, 0x2a);
+const MUTED: (u8, u8, u8) = (0x64, 0x74, 0x8b);
+const ACCENT: (u8, u8, u8) = (0xdc, 0x26, 0x26);
+
+/// The font card text is drawn with. Titles are drawn with its glyphs only, so it
+/// must cover every script used in post titles (e.g. a CJK font for Japanese).
+pub struct CardFont(FontVec);
+
+impl CardFont {
+    /// Loads a TrueType or OpenType font, or the first font of a collection.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, String> {
+        FontVec::try_from_vec(bytes)
+            .map(CardFont)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// What a social card shows.
+pub struct OgCard<'a> {
+    pub title: &'a str,
+    pub site_title: &'a str,
+    pub published_at: &'a str,
+    pub topics: &'a [String],
+    /// The blog icon, as PNG. Other formats are left out.
+    pub icon_png: Option<&'a [u8]>,
+}
+
+fn paint((r, g, b): (u8, u8, u8)) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, 0xff);
+    paint.anti_alias = true;
+    paint
+}
+
+fn text_width(font: &CardFont, size: f32, text: &str) -> f32 {
+    let scaled = font.0.as_scaled(PxScale::from(size));
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = font.0.glyph_id(c);
+        if let Some(previous) = previous {
+            width += scaled.kern(previous, id);
+        }
+        width += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+/// Draws `text` with its baseline starting at (`x`, `baseline`).
+fn draw_text(
+    pixmap: &mut Pixmap,
+    font: &CardFont,
+    size: f32,
+    text: &str,
+    (x, baseline): (f32, f32),
+    color: (u8, u8, u8),
+) {
+    let scaled = font.0.as_scaled(PxScale::from(size));
+    let (sx, sy) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+    let mut builder = PathBuilder::new();
+    let mut pen = x;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = font.0.glyph_id(c);
+        if let Some(previous) = previous {
+            pen += scaled.kern(previous, id);
+        }
+        if let Some(outline) = font.0.outline(id) {
+            // Font units have y pointing up
+            let at = |p: Point| (pen + p.x * sx, baseline - p.y * sy);
+            let mut last: Option<Point> = None;
+            for curve in outline.curves {
+                let (start, end) = match curve {
+                    OutlineCurve::Line(a, b) => (a, b),
+                    OutlineCurve::Quad(a, _, c) => (a, c),
+                    OutlineCurve::Cubic(a, _, _, d) => (a, d),
+                };
+                if last != Some(start) {
+                    let (x, y) = at(start);
+                    builder.move_to(x, y);
+                }
+                match curve {
+                    OutlineCurve::Line(_, b) => {
+                        let (x, y) = at(b);
+                        builder.line_to(x, y);
+                    }
+                    OutlineCurve::Quad(_, b, c) => {
+                        let ((x1, y1), (x, y)) = (at(b), at(c));
+                        builder.quad_to(x1, y1, x, y);
+                    }
+                    OutlineCurve::Cubic(_, b, c, d) => {
+                        let ((x1, y1), (x2, y2), (x, y)) = (at(b), at(c), at(d));
+                        builder.cubic_to(x1, y1, x2, y2, x, y);
+                    }
+                }
+                last = Some(end);
+            }
+        }
+        pen += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    if let Some(path) = builder.finish() {
+        pixmap.fill_path(
+            &path,
+            &paint(color),
+            FillRule::Winding,
+            Transform::identity(),
+            None,
+        );
+    }
+}
+
+/// Whether a line may break before `c`: CJK text has no spaces between words, so
+/// it can break between any two characters, except before closing punctuation.
+fn breaks_before(c: char) -> bool {
+    c >= '\u{2e80}' && !"、。，．・：；！？）」』】〕〉》ー…".contains(c)
+}
+
+/// Wraps `text` into lines no wider than `max_width`, breaking after spaces or
+/// between CJK characters, and within a word only if it is too long for a line.
+fn wrap(font: &CardFont, size: f32, text: &str, max_width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut break_at = None;
+    for c in text.chars() {
+        if breaks_before(c) && !line.is_empty() {
+            break_at = Some(line.len());
+        }
+        line.push(c);
+        if c.is_whitespace() {
+            break_at = Some(line.len());
+        }
+        if text_width(font, size, &line) > max_width && line.len() > c.len_utf8() {
+            let at = break_at
+                .filter(|&at| at < line.len())
+                .unwrap_or(line.len() - c.len_utf8());
+            let rest = line.split_off(at);
+            lines.push(line.trim_end().to_string());
+            line = rest.trim_start().to_string();
+            break_at = None;
+        }
+    }
+    if !line.trim().is_empty() {
+        lines.push(line.trim_end().to_string());
+    }
+    lines
+}
+
+/// Shortens `text` to fit `max_width` with an ellipsis.
+fn with_ellipsis(font: &CardFont, size: f32, text: &str, max_width: f32) -> String {
+    let mut text = text.trim_end().to_string();
+    while !text.is_empty() && text_width(font, size, &format!("{text}…")) > max_width {
+        text.pop();
+    }
+    format!("{}…", text.trim_end())
+}
+
+/// Keeps the first `max_lines` lines, ending the last one with an ellipsis if text
+/// was cut.
+fn truncate(
+    font: &CardFont,
+    size: f32,
+    mut lines: Vec<String>,
+    max_lines: usize,
+    max_width: f32,
+) -> Vec<String> {
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+        if let Some(last) = lines.last_mut() {
+            *last = with_ellipsis(font, size, last, max_width);
+        }
+    }
+    lines
+}
+
+/// Renders a social card as a PNG image of `CARD_WIDTH` by `CARD_HEIGHT` pixels.
+pub fn render_og_card(card: &OgCard, font: &CardFont) -> Result<Vec<u8>, String> {
+    let mut pixmap = Pixmap::new(CARD_WIDTH, CARD_HEIGHT).ok_or("invalid card size")?;
+    let (r, g, b) = BACKGROUND;
+    pixmap.fill(Color::from_rgba8(r, g, b, 0xff));
+    let width = CARD_WIDTH as f32;
+    let height = CARD_HEIGHT as f32;
+    let text_width_max = width - 2.0 * PADDING;
+
+    if let Some(bar) = Rect::from_xywh(0.0, 0.0, 16.0, height) {
+        pixmap.fill_rect(bar, &paint(ACCENT), Transform::identity(), None);
+    }
+
+    // Icon and site title
+    let mut x = PADDING;
+    if let Some(icon) = card.icon_png.and_then(|png| Pixmap::decode_png(png).ok()) {
+        let scale = ICON_SIZE / icon.width().max(icon.height()) as f32;
+        pixmap.draw_pixmap(
+            0,
+            0,
+            icon.as_ref(),
+            &PixmapPaint {
+                quality: FilterQuality::Bicubic,
+                ..PixmapPaint::default()
+            },
+            Transform::from_row(scale, 0.0, 0.0, scale, PADDING, PADDING),
+            None,
+        );
+        x += ICON_SIZE + 24.0;
+    }
+    let site_title_baseline = PADDING + ICON_SIZE / 2.0 + SITE_TITLE_SIZE * 0.35;
+    draw_text(
+        &mut pixmap,
+        font,
+        SITE_TITLE_SIZE,
+        card.site_title,
+        (x, site_title_baseline),
+        MUTED,
+    );
+
+    // Title
+    let lines = wrap(font, TITLE_SIZE, card.title, text_width_max);
+    let lines = truncate(font, TITLE_SIZE, lines, TITLE_MAX_LINES, text_width_max);
+    let line_height = TITLE_SIZE * 1.3;
+    let mut baseline = PADDING + ICON_SIZE + 56.0 + TITLE_SIZE;
+    for line in &lines {
+        draw_text(
+            &mut pixmap,
+            font,
+            TITLE_SIZE,
+            line,
+            (PADDING, baseline),
+            HEADING,
+        );
+        baseline += line_height;
+    }
+
+    // Date and tags
+    let mut footer = card.published_at.to_string();
+    for topic in card.topics {
+        footer.push_str(&format!("   #{topic}"));
+    }
+    if text_width(font, FOOTER_SIZE, &footer) > text_width_max {
+        footer = with_ellipsis(font, FOOTER_SIZE, &footer, text_width_max);
+    }
+    draw_text(
+        &mut pixmap,
+        font,
+        FOOTER_SIZE,
+        &footer,
+        (PADDING, height - PADDING),
+        MUTED,
+    );
+
+    pixmap.encode_png().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A font whose glyphs are boxes: ASCII characters and `…` are 500 units wide and
+    /// CJK characters 1000, with 1000 units per em, so at size 100 they are 50 and 100
+    /// pixels wide.
+    fn box_font() -> CardFont {
+        CardFont::from_bytes(include_bytes!("../fixtures/box.ttf").to_vec()).unwrap()
+    }
+
+    fn pixel(pixmap: &Pixmap, x: u32, y: u32) -> (u8, u8, u8) {
+        let pixel = pixmap.pixel(x, y).unwrap();
+        (pixel.red(), pixel.green(), pixel.blue())
+    }
+
+    #[test]
+    fn test_text_width() {
+        let font = box_font();
+
+        assert_eq!(text_width(&font, 100.0, "ab c"), 200.0);
+        assert_eq!(text_width(&font, 100.0, "日本"), 200.0);
+    }
+
+    #[test]
+    fn test_wrap() {
+        let font = box_font();
+
+        assert_eq!(
+            wrap(&font, 100.0, "aaa bbb ccc", 400.0),
+            vec!["aaa bbb", "ccc"]
+        );
+        // A word longer than a line is broken within
+        assert_eq!(
+            wrap(&font, 100.0, "aaaaaaaaaa b", 400.0),
+            vec!["aaaaaaaa", "aa b"]
+        );
+        // CJK text breaks between characters, but not before closing punctuation
+        assert_eq!(
+            wrap(&font, 100.0, "日本語のテスト。", 250.0),
+            vec!["日本", "語の", "テス", "ト。"]
+        );
+        assert!(wrap(&font, 100.0, "  ", 400.0).is_empty());
+    }
+
+    #[test]
+    fn test_truncate() {
+        let font = box_font();
+        let lines = wrap(&font, 100.0, "aaaaaaaa bbbbbbbb cccccccc dd", 400.0);
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            truncate(&font, 100.0, lines.clone(), 3, 400.0),
+            vec!["aaaaaaaa", "bbbbbbbb", "ccccccc…"]
+        );
+        assert_eq!(truncate(&font, 100.0, lines.clone(), 4, 400.0), lines);
+        assert_eq!(with_ellipsis(&font, 100.0, "ab ", 400.0), "ab…");
+    }
+
+    #[test]
+    fn test_render_og_card() {
+        let topics = vec!["rust".to_string()];
+        let card = OgCard {
+            title: "a",
+            site_title: "Blog",
+            published_at: "2024-01-02",
+            topics: &topics,
+            icon_png: None,
+        };
+
+        let png = render_og_card(&card, &box_font()).unwrap();
+        let pixmap = Pixmap::decode_png(&png).unwrap();
+
+        assert_eq!((pixmap.width(), pixmap.height()), (CARD_WIDTH, CARD_HEIGHT));
+        assert_eq!(pixel(&pixmap, 8, 300), ACCENT);
+        assert_eq!(pixel(&pixmap, 600, 300), BACKGROUND);
+        // The title's box glyph, between its baseline at 264 and its top at 219
+        assert_eq!(pixel(&pixmap, PADDING as u32 + 16, 240), HEADING);
+    }
+
+    #[test]
+    fn test_breaks_before() {
+        assert!(breaks_before('日'));
+        assert!(breaks_before('カ'));
+        assert!(!breaks_before('。'));
+        assert!(!breaks_before('ー'));
+        assert!(!breaks_before('a'));
+    }
+}
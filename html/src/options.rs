@@ -0,0 +1,50 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub use config::{CitationStyle, FootnoteStyle, RawHtml};
+
+/// Settings that control how a post's markdown is turned into HTML.
+#[derive(Debug, Default, Clone)]
+pub struct RenderOptions {
+    /// Directories that include directives such as `@code[...]` may read from.
+    /// When empty, includes are limited to the directory of the post itself.
+    pub include_roots: Vec<PathBuf>,
+    /// Treat warnings (such as a missing `@code` file) as errors that fail the post.
+    pub strict: bool,
+    /// Enables `@run[...]`. `None` leaves build-time execution disabled.
+    pub run: Option<RunOptions>,
+    /// Titles of the published posts by name, used to resolve `[[post-name]]` links.
+    pub post_titles: BTreeMap<String, String>,
+    /// How footnotes are presented, unless a post's front matter overrides it.
+    pub footnotes: FootnoteStyle,
+    /// Site-wide BibTeX file for `@cite[...]`, used when a post does not set its own.
+    pub bibliography: Option<PathBuf>,
+    /// How `@cite[...]` citations are rendered, unless a post's front matter overrides it.
+    pub citations: CitationStyle,
+    /// Where fenced code blocks are syntax highlighted.
+    pub highlighting: Highlighting,
+    /// What happens to HTML written directly in the markdown.
+    pub raw_html: RawHtml,
+}
+
+/// Where fenced code blocks are syntax highlighted.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Highlighting {
+    /// In the browser, by Prism loaded from a CDN.
+    #[default]
+    Prism,
+    /// At compile time, into spans styled by the stylesheet from [`crate::highlight::highlight_css`].
+    Static,
+}
+
+/// Settings for executing scripts referenced by `@run[...]`.
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    /// Maps a file extension (without the dot) to the command that runs it, e.g. `py` -> `python3 -u`.
+    pub interpreters: BTreeMap<String, String>,
+    pub timeout: Duration,
+    /// Where the output of successful runs is cached, keyed by a hash of the interpreter,
+    /// the script and the files next to it. `None` disables caching.
+    pub cache_dir: Option<PathBuf>,
+}
@@ -1,31 +1,61 @@
+use crate::code_fence::CODE_COPY_SCRIPT;
+use crate::code_tabs::CODE_TABS_SCRIPT;
+use crate::escape::escape_html;
 use crate::metadata::Post;
+use crate::svg_icon::Icon;
 
+/// Renders a post page. `head_html` is extra markup for `<head>`, such as the icon link
+/// and the code highlighting assets.
 pub fn post_html(
     post: &Post,
     site_title: &str,
     aside_html: &str,
     footer_html: &str,
-    icon_html: &str,
+    head_html: &str,
     css_file_path: &str,
+    backlinks_html: &str,
 ) -> String {
     // Generate the header for the post
     let header_html = format!(
         r#"<header>
   <h1>{title}</h1>
   <div class="post-meta">
-    <span class="published-at">{published_at}</span>
+    <span class="published-at">{calendar_icon} {published_at}</span>
+    {topics}
     {summary}
   </div>
 </header>"#,
-        title = post.meta.title,
-        published_at = post.meta.published_at,
+        title = escape_html(&post.meta.title),
+        calendar_icon = Icon::Calendar.svg(),
+        published_at = escape_html(&post.meta.published_at),
+        topics = if post.meta.topics.is_empty() {
+            String::new()
+        } else {
+            format!(
+                r#"<span class="topics">{tag_icon} {topics}</span>"#,
+                tag_icon = Icon::Tag.svg(),
+                topics = escape_html(&post.meta.topics.join(", "))
+            )
+        },
         summary = if let Some(tldr) = &post.meta.tldr {
-            format!(r#"<p class="summary">{tldr}</p>"#)
+            format!(r#"<p class="summary">{}</p>"#, escape_html(tldr))
         } else {
             String::new()
         }
     );
 
+    // Only pages with tabbed code groups need the tabs script
+    let code_tabs_script = if post.html_body.contains(r#"class="code-tabs""#) {
+        format!("<script>{CODE_TABS_SCRIPT}</script>")
+    } else {
+        String::new()
+    };
+    let code_copy_script = if post.html_body.contains(r#"class="code-copy""#) {
+        format!("<script>{CODE_COPY_SCRIPT}</script>")
+    } else {
+        String::new()
+    };
+
     // Final HTML assembly
     let og_description = post.meta.tldr.as_deref().unwrap_or(site_title);
     format!(
@@ -40,16 +70,7 @@ pub fn post_html(
   <meta property="og:type" content="article" />
   <meta property="og:site_name" content="{site_title}" />
   <link rel="stylesheet" href="{css_file_path}">
-  <link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/prismjs@1.29.0/themes/prism-tomorrow.min.css">
-  <script src="https://cdn.jsdelivr.net/npm/prismjs@1.29.0/components/prism-core.min.js"></script>
-  <script src="https://cdn.jsdelivr.net/npm/prismjs@1.29.0/plugins/autoloader/prism-autoloader.min.js"></script>
-  <script>
-    // Load languages on demand from the CDN
-    if (window.Prism && Prism.plugins.autoloader) {{
-      Prism.plugins.autoloader.languages_path = "https://cdn.jsdelivr.net/npm/prismjs@1.29.0/components/";
-    }}
-  </script>
-  {icon_html}
+  {head_html}
 </head>
 <body>
   <div class="container">
@@ -59,22 +80,58 @@ pub fn post_html(
       <article>
         {body}
       </article>
+      {backlinks_html}
     </main>
   </div>
   {footer_html}
-  <script>Prism.highlightAll();</script>
+  {code_tabs_script}
+  {code_copy_script}
 </body>
 </html>
 "#,
-        title = post.meta.title,
-        og_title = post.meta.title,
-        og_description = og_description,
-        site_title = site_title,
-        css_file_path = css_file_path,
-        icon_html = icon_html,
+        title = escape_html(&post.meta.title),
+        og_title = escape_html(&post.meta.title),
+        og_description = escape_html(og_description),
+        site_title = escape_html(site_title),
+        css_file_path = escape_html(css_file_path),
+        head_html = head_html,
         aside_html = aside_html,
         header_html = header_html,
         body = post.html_body,
-        footer_html = footer_html
+        backlinks_html = backlinks_html,
+        footer_html = footer_html,
+        code_tabs_script = code_tabs_script,
+        code_copy_script = code_copy_script
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::BlogMeta;
+
+    #[test]
+    fn test_post_html_escapes_metadata() {
+        let front_matter = "---\ntitle: '</title><script>alert(\"x\")</script> & co'\ntopics: ['<b>']\npublished: true\npublished_at: 2024-01-01\ntldr: 'A \"quoted\" <summary>'\n---\n";
+        let (meta, _) = BlogMeta::from_markdown_str(front_matter).unwrap();
+        let post = Post {
+            meta,
+            html_body: "<p>Body</p>".to_string(),
+            markdown_body: String::new(),
+            name: "hostile".to_string(),
+            diagnostics: Vec::new(),
+            links: Vec::new(),
+        };
+
+        let html = post_html(&post, "Site <&>", "", "", "", "style.css", "");
+
+        assert!(!html.contains("<script>alert"));
+        assert!(html.contains("<title>&lt;/title&gt;&lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt; &amp; co</title>"));
+        assert!(html.contains(
+            r#"<meta property="og:description" content="A &quot;quoted&quot; &lt;summary&gt;" />"#
+        ));
+        assert!(html.contains(r#"<meta property="og:site_name" content="Site &lt;&amp;&gt;" />"#));
+        assert!(html.contains("&lt;b&gt;</span>"));
+        assert!(html.contains("<p>Body</p>"));
+    }
+}
@@ -0,0 +1,172 @@
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag, TagEnd};
+use std::ops::Range;
+
+/// Finds the code in `markdown`: the byte ranges of code blocks (fenced or indented,
+/// including their fences) and of inline code spans.
+pub(crate) fn code_ranges(markdown: &str) -> Vec<Range<usize>> {
+    Parser::new_ext(markdown, Options::ENABLE_FOOTNOTES)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Code(_) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Parts of links and images in `markdown` that are not shown as prose.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct LinkRanges {
+    /// Byte ranges of image alt texts, which end up in an attribute.
+    pub alt_texts: Vec<Range<usize>>,
+    /// Byte ranges of link and image destinations: everything after the text, such as
+    /// `](url "title")` or `][label]`, or a whole autolink.
+    pub destinations: Vec<Range<usize>>,
+}
+
+/// A link or image that has started but not ended yet.
+struct OpenLink {
+    image: bool,
+    autolink: bool,
+    range: Range<usize>,
+    /// End of the link text seen so far.
+    text_end: usize,
+}
+
+/// Finds the [`LinkRanges`] of `markdown`.
+pub(crate) fn link_ranges(markdown: &str) -> LinkRanges {
+    let mut ranges = LinkRanges::default();
+    let mut open: Vec<OpenLink> = Vec::new();
+    for (event, range) in Parser::new_ext(markdown, Options::ENABLE_FOOTNOTES).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Link { link_type, .. }) => open.push(OpenLink {
+                image: false,
+                autolink: matches!(link_type, LinkType::Autolink | LinkType::Email),
+                text_end: range.start + "[".len(),
+                range,
+            }),
+            Event::Start(Tag::Image { .. }) => open.push(OpenLink {
+                image: true,
+                autolink: false,
+                text_end: range.start + "![".len(),
+                range,
+            }),
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                let Some(link) = open.pop() else {
+                    continue;
+                };
+                // A nested link or image is part of the text of its parent
+                if let Some(parent) = open.last_mut() {
+                    parent.text_end = link.range.end;
+                }
+                if link.autolink {
+                    ranges.destinations.push(link.range);
+                    continue;
+                }
+                if link.image {
+                    ranges
+                        .alt_texts
+                        .push(link.range.start + "![".len()..link.text_end);
+                }
+                ranges.destinations.push(link.text_end..link.range.end);
+            }
+            _ => {
+                if let Some(link) = open.last_mut() {
+                    link.text_end = link.text_end.max(range.end);
+                }
+            }
+        }
+    }
+    ranges
+}
+
+/// Applies `f` to the text of `markdown` outside code blocks and inline code spans,
+/// so inline syntax such as `[[post]]` is never rewritten inside code.
+///
+/// `f` is called once per stretch of prose, which never spans more than one line.
+pub(crate) fn map_prose(markdown: &str, mut f: impl FnMut(&str) -> String) -> String {
+    map_prose_at(markdown, |text, _| f(text))
+}
+
+/// Like [`map_prose`], but `f` also receives the byte offset of the text in `markdown`.
+pub(crate) fn map_prose_at(markdown: &str, mut f: impl FnMut(&str, usize) -> String) -> String {
+    let mut output = String::with_capacity(markdown.len());
+    let mut last = 0;
+    let end = markdown.len()..markdown.len();
+
+    for code in code_ranges(markdown).into_iter().chain([end]) {
+        let mut offset = last;
+        for (i, line) in markdown[last..code.start].split('\n').enumerate() {
+            if i > 0 {
+                output.push('\n');
+                offset += 1;
+            }
+            output.push_str(&f(line, offset));
+            offset += line.len();
+        }
+        output.push_str(&markdown[code.clone()]);
+        last = code.end;
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_prose_skips_code() {
+        let markdown = "a `b` ``c ` d`` e\n```\nf\n```\ng ` h";
+        let result = map_prose(markdown, |text| text.to_uppercase());
+
+        assert_eq!(result, "A `b` ``c ` d`` E\n```\nf\n```\nG ` H");
+    }
+
+    #[test]
+    fn test_map_prose_skips_indented_and_nested_fences() {
+        let markdown = "a\n\n    b {x|y}\n\n````\n```\nc\n```\n````\nd";
+        let result = map_prose(markdown, |text| text.to_uppercase());
+
+        assert_eq!(result, "A\n\n    b {x|y}\n\n````\n```\nc\n```\n````\nD");
+    }
+
+    #[test]
+    fn test_link_ranges() {
+        let markdown = "[a](x) ![b *c*](y.png \"t\") [![d](z.png)][ref] <https://e>\n\n[ref]: w";
+        let ranges = link_ranges(markdown);
+        let slices = |ranges: &[Range<usize>]| -> Vec<&str> {
+            ranges.iter().map(|r| &markdown[r.clone()]).collect()
+        };
+
+        assert_eq!(slices(&ranges.alt_texts), ["b *c*", "d"]);
+        assert_eq!(
+            slices(&ranges.destinations),
+            [
+                "](x)",
+                "](y.png \"t\")",
+                "](z.png)",
+                "][ref]",
+                "<https://e>"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_map_prose_at_passes_offsets() {
+        let markdown = "ab `c`\nde";
+        let mut seen = Vec::new();
+        map_prose_at(markdown, |text, offset| {
+            seen.push((text.to_string(), offset));
+            text.to_string()
+        });
+
+        assert_eq!(
+            seen,
+            [
+                ("ab ".to_string(), 0),
+                (String::new(), 6),
+                ("de".to_string(), 7)
+            ]
+        );
+    }
+}
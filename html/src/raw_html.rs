@@ -0,0 +1,570 @@
+use pulldown_cmark::{Event, Tag, TagEnd};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use crate::escape::escape_html;
+use crate::options::RawHtml;
+
+/// Tags kept by [`RawHtml::Sanitize`].
+const ALLOWED_TAGS: &[&str] = &[
+    "a",
+    "abbr",
+    "b",
+    "blockquote",
+    "br",
+    "caption",
+    "cite",
+    "code",
+    "col",
+    "colgroup",
+    "dd",
+    "del",
+    "details",
+    "dfn",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "figcaption",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "hr",
+    "i",
+    "img",
+    "ins",
+    "kbd",
+    "li",
+    "mark",
+    "ol",
+    "p",
+    "pre",
+    "q",
+    "rp",
+    "rt",
+    "ruby",
+    "s",
+    "samp",
+    "small",
+    "span",
+    "strong",
+    "sub",
+    "summary",
+    "sup",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "time",
+    "tr",
+    "u",
+    "ul",
+    "var",
+];
+
+/// Tags whose content is removed along with them, since it is not meant to be shown.
+const DROPPED_WITH_CONTENT: &[&str] = &[
+    "script", "style", "iframe", "object", "noscript", "template", "textarea", "title",
+];
+
+/// Attributes kept on any allowed tag.
+const GLOBAL_ATTRIBUTES: &[&str] = &["class", "id", "title", "lang", "dir"];
+
+/// Attributes kept on specific tags.
+fn is_allowed_attribute(tag: &str, attribute: &str) -> bool {
+    GLOBAL_ATTRIBUTES.contains(&attribute)
+        || matches!(
+            (tag, attribute),
+            ("a", "href")
+                | ("img", "src" | "alt" | "width" | "height" | "loading")
+                | ("td" | "th", "colspan" | "rowspan")
+                | ("col" | "colgroup", "span")
+                | ("ol", "start" | "reversed")
+                | ("time", "datetime")
+                | ("details", "open")
+                | ("q" | "blockquote" | "del" | "ins", "cite")
+        )
+}
+
+fn is_url_attribute(attribute: &str) -> bool {
+    matches!(attribute, "href" | "src" | "cite")
+}
+
+/// Whether a URL is relative or uses `http`, `https` or `mailto`.
+pub(crate) fn is_safe_url(url: &str) -> bool {
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    // Character references could hide a scheme such as `javascript&#58;`
+    if url.contains("&#") || url.contains("&colon") {
+        return false;
+    }
+    match url.find([':', '/', '?', '#']) {
+        Some(i) if url[i..].starts_with(':') => {
+            matches!(&url[..i], "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+/// A change made to a post's raw HTML.
+#[derive(Debug, PartialEq)]
+pub struct RawHtmlChange {
+    /// Byte offset in the markdown of the HTML that was changed.
+    pub pos: usize,
+    pub message: String,
+}
+
+/// An attribute of a start tag, with its span in the tag's text.
+pub(crate) struct Attribute<'a> {
+    /// Lowercased name.
+    pub name: String,
+    pub value: &'a str,
+    pub span: Range<usize>,
+}
+
+/// Parses the attributes of the start tag `tag`, which starts after its name at `start`.
+/// As in browsers, a `/` that does not end the tag separates attributes like whitespace.
+pub(crate) fn parse_attributes(tag: &str, start: usize) -> Vec<Attribute<'_>> {
+    let bytes = tag.as_bytes();
+    let mut attributes = Vec::new();
+    let mut i = start;
+    loop {
+        while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i >= bytes.len() || bytes[i] == b'>' {
+            return attributes;
+        }
+        if bytes[i] == b'/' {
+            if bytes.get(i + 1).is_none_or(|&b| b == b'>') {
+                return attributes;
+            }
+            i += 1;
+            continue;
+        }
+        let name_start = i;
+        while i < bytes.len() && !bytes[i].is_ascii_whitespace() && !b"/>=".contains(&bytes[i]) {
+            i += 1;
+        }
+        let name = tag[name_start..i].to_ascii_lowercase();
+        let mut value = "";
+        let mut j = i;
+        while j < bytes.len() && bytes[j].is_ascii_whitespace() {
+            j += 1;
+        }
+        if j < bytes.len() && bytes[j] == b'=' {
+            j += 1;
+            while j < bytes.len() && bytes[j].is_ascii_whitespace() {
+                j += 1;
+            }
+            if j < bytes.len() && (bytes[j] == b'"' || bytes[j] == b'\'') {
+                let quote = bytes[j];
+                let value_start = j + 1;
+                j = value_start;
+                while j < bytes.len() && bytes[j] != quote {
+                    j += 1;
+                }
+                value = &tag[value_start..j];
+                j = (j + 1).min(bytes.len());
+            } else {
+                let value_start = j;
+                while j < bytes.len() && !bytes[j].is_ascii_whitespace() && bytes[j] != b'>' {
+                    j += 1;
+                }
+                value = &tag[value_start..j];
+            }
+            i = j;
+        }
+        attributes.push(Attribute {
+            name,
+            value,
+            span: name_start..i,
+        });
+    }
+}
+
+/// Finds the `>` that ends the tag at the start of `html`, skipping quoted values.
+pub(crate) fn tag_end(html: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in html.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Elements without a closing tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// The attribute [`generated`] adds to mejiro's own start tags. It contains a random
+/// value chosen at startup, so post content cannot forge it.
+fn marker() -> &'static str {
+    static MARKER: OnceLock<String> = OnceLock::new();
+    MARKER.get_or_init(|| {
+        let hash = RandomState::new().build_hasher().finish();
+        format!("data-mejiro-{hash:016x}")
+    })
+}
+
+/// Marks every start tag in `html` as generated by mejiro, so that the `raw_html`
+/// policy leaves it alone. `html` must not contain post content other than escaped text.
+pub(crate) fn generated(html: &str) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(lt) = rest.find('<') {
+        output.push_str(&rest[..=lt]);
+        rest = &rest[lt + 1..];
+        if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let name_len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            output.push_str(&rest[..name_len]);
+            output.push(' ');
+            output.push_str(marker());
+            rest = &rest[name_len..];
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Removes the markers added by [`generated`], for comparing generated HTML in tests.
+#[cfg(test)]
+pub(crate) fn without_markers(html: &str) -> String {
+    html.replace(&format!(" {}", marker()), "")
+}
+
+/// Applies the `raw_html` policy to the raw HTML of a post, except for the tags mejiro
+/// generated (see [`generated`]), whose marker is removed.
+///
+/// HTML in a block may span several chunks (one per line inside a blockquote or list),
+/// so the element whose content is being dropped and the open generated elements carry
+/// over between chunks.
+struct Sanitizer {
+    policy: RawHtml,
+    dropping: Option<String>,
+    /// Generated elements that have not been closed yet.
+    generated: Vec<String>,
+    changes: Vec<RawHtmlChange>,
+}
+
+impl Sanitizer {
+    fn new(policy: RawHtml) -> Self {
+        Sanitizer {
+            policy,
+            dropping: None,
+            generated: Vec::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    fn report(&mut self, pos: usize, message: String) {
+        self.changes.push(RawHtmlChange { pos, message });
+    }
+
+    /// Filters `html`, the chunk of raw HTML at byte `offset` of the markdown.
+    fn filter(&mut self, html: &str, offset: usize) -> String {
+        let mut output = String::with_capacity(html.len());
+        let mut rest = html;
+        let mut escaped = false;
+
+        while !rest.is_empty() {
+            let pos = offset + html.len() - rest.len();
+
+            if let Some(name) = &self.dropping {
+                let close = format!("</{name}");
+                match rest.to_ascii_lowercase().find(&close) {
+                    Some(end) => {
+                        let tag_end = rest[end..].find('>').map_or(rest.len(), |i| end + i + 1);
+                        rest = &rest[tag_end..];
+                        self.dropping = None;
+                    }
+                    None => rest = "",
+                }
+                continue;
+            }
+
+            let lt = rest.find('<').unwrap_or(rest.len());
+            // Text inside generated elements was escaped when it was generated
+            if self.policy == RawHtml::Deny && self.generated.is_empty() {
+                output.push_str(&escape_html(&rest[..lt]));
+            } else {
+                output.push_str(&rest[..lt]);
+            }
+            rest = &rest[lt..];
+            if rest.is_empty() {
+                break;
+            }
+            let pos = pos + lt;
+
+            let end = if rest.starts_with("<!--") {
+                rest.find("-->").map(|i| i + 2)
+            } else {
+                tag_end(rest)
+            };
+            let Some(end) = end else {
+                match self.policy {
+                    RawHtml::Allow => output.push_str(rest),
+                    RawHtml::Sanitize => {
+                        self.report(pos, "removed incomplete tag from raw HTML".to_string());
+                    }
+                    RawHtml::Deny => {
+                        self.escape(&mut output, rest, pos, escaped);
+                    }
+                }
+                break;
+            };
+            let tag = &rest[..=end];
+            rest = &rest[end + 1..];
+
+            let closing = tag.starts_with("</");
+            let name_start = if closing { 2 } else { 1 };
+            let name_len = tag[name_start..]
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(0);
+            let name = tag[name_start..name_start + name_len].to_ascii_lowercase();
+
+            if closing && let Some(i) = self.generated.iter().rposition(|n| *n == name) {
+                self.generated.remove(i);
+                output.push_str(&format!("</{name}>"));
+            } else if !closing
+                && !name.is_empty()
+                && parse_attributes(tag, name_start + name_len)
+                    .iter()
+                    .any(|attribute| attribute.name == marker())
+            {
+                if !VOID_ELEMENTS.contains(&name.as_str()) && !tag.ends_with("/>") {
+                    self.generated.push(name);
+                }
+                output.push_str(&tag.replacen(&format!(" {}", marker()), "", 1));
+            } else if self.policy == RawHtml::Allow {
+                output.push_str(tag);
+            } else if self.policy == RawHtml::Deny {
+                escaped = self.escape(&mut output, tag, pos, escaped);
+            } else if tag.starts_with("<!--") {
+                output.push_str(tag);
+            } else if name.is_empty() {
+                self.report(pos, format!("removed `{tag}` from raw HTML"));
+            } else if !ALLOWED_TAGS.contains(&name.as_str()) {
+                if !closing {
+                    if DROPPED_WITH_CONTENT.contains(&name.as_str()) {
+                        self.report(
+                            pos,
+                            format!("removed `<{name}>` and its content from raw HTML"),
+                        );
+                        if !tag.ends_with("/>") {
+                            self.dropping = Some(name);
+                        }
+                    } else {
+                        self.report(pos, format!("removed `<{name}>` tag from raw HTML"));
+                    }
+                }
+            } else if closing {
+                output.push_str(&format!("</{name}>"));
+            } else {
+                output.push_str(&self.sanitize_start_tag(tag, &name, name_start + name_len, pos));
+            }
+        }
+
+        output
+    }
+
+    /// Escapes `html` so it shows as text, reporting the first escape of a chunk.
+    fn escape(&mut self, output: &mut String, html: &str, pos: usize, reported: bool) -> bool {
+        if !reported {
+            let snippet = html.lines().next().unwrap_or_default().trim();
+            self.report(pos, format!("escaped raw HTML `{snippet}`"));
+        }
+        output.push_str(&escape_html(html));
+        true
+    }
+
+    /// Removes the disallowed attributes of an allowed start tag.
+    fn sanitize_start_tag(
+        &mut self,
+        tag: &str,
+        name: &str,
+        attributes_start: usize,
+        pos: usize,
+    ) -> String {
+        let mut output = String::with_capacity(tag.len());
+        let mut last = 0;
+        for attribute in parse_attributes(tag, attributes_start) {
+            let reason = if !is_allowed_attribute(name, &attribute.name) {
+                Some(format!("removed `{}` attribute", attribute.name))
+            } else if is_url_attribute(&attribute.name) && !is_safe_url(attribute.value) {
+                Some(format!("removed unsafe URL in `{}`", attribute.name))
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                self.report(pos, format!("{reason} from `<{name}>` in raw HTML"));
+                output.push_str(&tag[last..attribute.span.start]);
+                last = attribute.span.end;
+            }
+        }
+        output.push_str(&tag[last..]);
+        output
+    }
+}
+
+/// Applies the `raw_html` policy to the `Html` and `InlineHtml` events of a post, after
+/// directives and includes have been expanded, so it covers HTML from every source.
+///
+/// With [`RawHtml::Sanitize`], tags and attributes outside a safe allowlist are removed
+/// (with the content of elements such as `<script>`), as are links with a scheme other
+/// than `http`, `https` or `mailto`. With [`RawHtml::Deny`], raw HTML is escaped so it
+/// shows as text. Markup generated by mejiro is kept with every policy, and HTML in code
+/// is not raw HTML.
+///
+/// Takes the events with their byte ranges in the markdown, and returns the filtered
+/// events and what was changed, for reporting. The lines of an HTML block are merged
+/// into one event.
+pub(crate) fn filter_raw_html<'a>(
+    events: impl Iterator<Item = (Event<'a>, Range<usize>)>,
+    policy: RawHtml,
+) -> (Vec<Event<'a>>, Vec<RawHtmlChange>) {
+    let mut output = Vec::new();
+    let mut sanitizer = Sanitizer::new(policy);
+    let mut block: Option<(String, usize)> = None;
+    for (event, range) in events {
+        match event {
+            Event::Start(Tag::HtmlBlock) => block = Some((String::new(), range.start)),
+            Event::Html(html) => match &mut block {
+                Some((block, _)) => block.push_str(&html),
+                None => output.push(Event::Html(sanitizer.filter(&html, range.start).into())),
+            },
+            Event::End(TagEnd::HtmlBlock) => {
+                if let Some((html, start)) = block.take() {
+                    output.push(Event::Start(Tag::HtmlBlock));
+                    output.push(Event::Html(sanitizer.filter(&html, start).into()));
+                    output.push(Event::End(TagEnd::HtmlBlock));
+                }
+            }
+            Event::InlineHtml(html) => {
+                output.push(Event::InlineHtml(
+                    sanitizer.filter(&html, range.start).into(),
+                ));
+            }
+            // The content of a dropped inline element, such as `<script>`
+            Event::Text(_) if sanitizer.dropping.is_some() => {}
+            event => output.push(event),
+        }
+    }
+    (output, sanitizer.changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulldown_cmark::{Options, Parser, html};
+
+    fn filter(markdown: &str, policy: RawHtml) -> (String, Vec<RawHtmlChange>) {
+        let parser = Parser::new_ext(markdown, Options::ENABLE_FOOTNOTES).into_offset_iter();
+        let (events, changes) = filter_raw_html(parser, policy);
+        let mut output = String::new();
+        html::push_html(&mut output, events.into_iter());
+        (output, changes)
+    }
+
+    #[test]
+    fn test_filter_raw_html_sanitize() {
+        let markdown = "Hi <span class=\"x\" title='>' onclick=\"evil()\">there</span> <a href=\"javascript:alert(1)\">x</a>\n\n<script>\nalert(1)\n</script>\n\n<div id=\"a\">\n<iframe src=\"https://example.com\"></iframe>\n</div>\n\n`<script>` stays\n\n<div>\n<img/onerror=alert(1) src=x>\n<a href=\"x\"/onclick=alert(1)>link</a><br/>\n</div>\n";
+        let (result, changes) = filter(markdown, RawHtml::Sanitize);
+
+        assert_eq!(
+            result,
+            "<p>Hi <span class=\"x\" title='>' >there</span> <a >x</a></p>\n\n<div id=\"a\">\n\n</div>\n<p><code>&lt;script&gt;</code> stays</p>\n<div>\n<img/ src=x>\n<a href=\"x\"/>link</a><br/>\n</div>\n"
+        );
+        let messages: Vec<&str> = changes.iter().map(|c| c.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "removed `onclick` attribute from `<span>` in raw HTML",
+                "removed unsafe URL in `href` from `<a>` in raw HTML",
+                "removed `<script>` and its content from raw HTML",
+                "removed `<iframe>` and its content from raw HTML",
+                "removed `onerror` attribute from `<img>` in raw HTML",
+                "removed `onclick` attribute from `<a>` in raw HTML",
+            ]
+        );
+        assert_eq!(changes[2].pos, markdown.find("<script>\n").unwrap());
+    }
+
+    #[test]
+    fn test_filter_raw_html_deny() {
+        let markdown = "A <b>bold</b> move.\n\n<div>\nblock & more\n</div>\n";
+        let (result, changes) = filter(markdown, RawHtml::Deny);
+
+        assert_eq!(
+            result,
+            "<p>A &lt;b&gt;bold&lt;/b&gt; move.</p>\n&lt;div&gt;\nblock &amp; more\n&lt;/div&gt;\n"
+        );
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes[2].message, "escaped raw HTML `<div>`");
+    }
+
+    #[test]
+    fn test_filter_raw_html_keeps_generated_markup() {
+        let markdown = format!(
+            "{}\n\n<script>alert(1)</script>\n\n{}*cell* <img src=x onerror=alert(2)>\n\n{}\n",
+            generated("<div class=\"notebook\" role=\"note\">"),
+            generated("<button>A &amp; B</button>"),
+            "</div>"
+        );
+
+        let (result, _) = filter(&markdown, RawHtml::Sanitize);
+        assert_eq!(
+            result,
+            "<div class=\"notebook\" role=\"note\">\n\n<p><button>A &amp; B</button><em>cell</em> <img src=x ></p>\n</div>\n"
+        );
+
+        let (result, changes) = filter(&markdown, RawHtml::Deny);
+        assert!(result.starts_with(
+            "<div class=\"notebook\" role=\"note\">\n&lt;script&gt;alert(1)&lt;/script&gt;\n"
+        ));
+        assert!(result.contains("<button>A &amp; B</button><em>cell</em> &lt;img"));
+        assert_eq!(changes.len(), 2);
+
+        let (result, changes) = filter(&markdown, RawHtml::Allow);
+        assert!(!result.contains("data-mejiro"));
+        assert!(result.contains("<script>alert(1)</script>"));
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn test_generated_marker_cannot_be_forged() {
+        let markdown = "<div data-mejiro-0000000000000000><script>x</script></div>\n";
+        let (result, _) = filter(markdown, RawHtml::Sanitize);
+        assert_eq!(result, "<div ></div>\n");
+    }
+    #[test]
+    fn test_is_safe_url() {
+        assert!(is_safe_url("https://example.com/a:b"));
+        assert!(is_safe_url("../posts/a.html#x"));
+        assert!(is_safe_url("mailto:me@example.com"));
+        assert!(!is_safe_url(" JavaScript:alert(1)"));
+        assert!(!is_safe_url("java\nscript:alert(1)"));
+        assert!(!is_safe_url("javascript&#58;alert(1)"));
+        assert!(!is_safe_url("data:text/html,x"));
+    }
+}
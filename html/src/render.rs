@@ -0,0 +1,250 @@
+use pulldown_cmark::{CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd, html};
+use std::collections::HashMap;
+
+use crate::code_fence::{FenceInfo, code_block_html};
+use crate::options::{FootnoteStyle, Highlighting, RawHtml};
+use crate::raw_html::{RawHtmlChange, filter_raw_html};
+
+/// Renders footnote content for use inside a paragraph: paragraphs are joined with
+/// `<br>` instead of being wrapped in `<p>`.
+fn inline_note_html(events: &[Event]) -> String {
+    let mut first = true;
+    let events = events.iter().cloned().filter_map(|event| match event {
+        Event::Start(Tag::Paragraph) if first => {
+            first = false;
+            None
+        }
+        Event::Start(Tag::Paragraph) => Some(Event::InlineHtml("<br>".into())),
+        Event::End(TagEnd::Paragraph) => None,
+        event => Some(event),
+    });
+    let mut output = String::new();
+    html::push_html(&mut output, events);
+    output.trim_end().to_string()
+}
+
+/// Renders the markup for a footnote reference.
+///
+/// Besides the numbered link to the end-of-article note, the note's content is repeated
+/// next to the reference, as a margin note or as a preview shown on hover and focus.
+fn reference_html(number: usize, first: bool, note: &[Event], style: FootnoteStyle) -> String {
+    let id = if first {
+        format!(r#" id="fnref-{number}""#)
+    } else {
+        String::new()
+    };
+    let reference =
+        format!(r##"<sup class="footnote-ref"{id}><a href="#fn-{number}">{number}</a></sup>"##);
+    let note = inline_note_html(note);
+    match style {
+        FootnoteStyle::End => format!(
+            r#"<span class="footnote">{reference}<span class="footnote-preview" role="tooltip">{note}</span></span>"#
+        ),
+        FootnoteStyle::Sidenotes => format!(
+            r#"{reference}<span class="sidenote" role="note"><span class="sidenote-number">{number}</span> {note}</span>"#
+        ),
+    }
+}
+
+/// Renders the numbered list of notes placed at the end of the article.
+fn footnotes_html(
+    order: &[String],
+    definitions: &HashMap<String, Vec<Event>>,
+    style: FootnoteStyle,
+) -> String {
+    let class = match style {
+        FootnoteStyle::End => "footnotes",
+        FootnoteStyle::Sidenotes => "footnotes footnotes-sidenotes",
+    };
+    let mut output = format!("<section class=\"{class}\">\n<ol>\n");
+    for (i, label) in order.iter().enumerate() {
+        let number = i + 1;
+        let mut note = String::new();
+        html::push_html(&mut note, definitions[label].iter().cloned());
+        output.push_str(&format!(
+            r##"<li id="fn-{number}">
+{note}<a href="#fnref-{number}" class="footnote-backref" aria-label="Back to reference {number}">↩</a>
+</li>
+"##
+        ));
+    }
+    output.push_str("</ol>\n</section>\n");
+    output
+}
+
+/// Replaces each code block with [`code_block_html`], which honours the options in the
+/// info string and highlights the code when `highlighting` is [`Highlighting::Static`].
+fn render_code_blocks<'a>(
+    events: impl Iterator<Item = Event<'a>>,
+    highlighting: Highlighting,
+) -> Vec<Event<'a>> {
+    let mut output = Vec::new();
+    let mut block: Option<(FenceInfo, String)> = None;
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let info = match kind {
+                    CodeBlockKind::Fenced(info) => FenceInfo::parse(&info),
+                    CodeBlockKind::Indented => FenceInfo::default(),
+                };
+                block = Some((info, String::new()));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((info, code)) = block.take() {
+                    let html = code_block_html(&code, &info, highlighting);
+                    output.push(Event::Html(CowStr::from(html)));
+                }
+            }
+            Event::Text(text) if block.is_some() => {
+                if let Some((_, code)) = &mut block {
+                    code.push_str(&text);
+                }
+            }
+            event => output.push(event),
+        }
+    }
+    output
+}
+
+/// Renders post markdown to HTML.
+///
+/// Footnotes (`[^label]` with a `[^label]: ...` definition) are numbered in order of first
+/// reference and collected at the end of the article. With
+/// [`FootnoteStyle::Sidenotes`], each note is also placed in the margin next to its
+/// reference, which the default CSS shows instead of the end notes on wide screens.
+///
+/// Code blocks get a copy button, plus a filename header, line numbers and highlighted
+/// lines when their info string asks for them (see [`FenceInfo`]). They are
+/// highlighted here when `highlighting` is [`Highlighting::Static`].
+///
+/// The `raw_html` policy is applied to the raw HTML of the post, which by now includes
+/// the content of every include. Returns the HTML and what the policy changed.
+pub(crate) fn render_markdown(
+    markdown: &str,
+    footnotes: FootnoteStyle,
+    highlighting: Highlighting,
+    raw_html: RawHtml,
+) -> (String, Vec<RawHtmlChange>) {
+    let parser = Parser::new_ext(markdown, Options::ENABLE_FOOTNOTES).into_offset_iter();
+    let (events, changes) = filter_raw_html(parser, raw_html);
+    let parser = render_code_blocks(events.into_iter(), highlighting);
+
+    // Pull footnote definitions out of the document flow
+    let mut events: Vec<Event> = Vec::new();
+    let mut definitions: HashMap<String, Vec<Event>> = HashMap::new();
+    let mut definition: Option<(String, Vec<Event>)> = None;
+    for event in parser {
+        match event {
+            Event::Start(Tag::FootnoteDefinition(label)) => {
+                definition = Some((label.to_string(), Vec::new()));
+            }
+            Event::End(TagEnd::FootnoteDefinition) => {
+                if let Some((label, body)) = definition.take() {
+                    definitions.entry(label).or_insert(body);
+                }
+            }
+            event => match &mut definition {
+                Some((_, body)) => body.push(event),
+                None => events.push(event),
+            },
+        }
+    }
+
+    let mut order: Vec<String> = Vec::new();
+    let events: Vec<Event> = events
+        .into_iter()
+        .map(|event| match event {
+            Event::FootnoteReference(label) if definitions.contains_key(label.as_ref()) => {
+                let index = order.iter().position(|l| l == label.as_ref());
+                let first = index.is_none();
+                let number = index.unwrap_or_else(|| {
+                    order.push(label.to_string());
+                    order.len() - 1
+                }) + 1;
+                let html = reference_html(number, first, &definitions[label.as_ref()], footnotes);
+                Event::InlineHtml(CowStr::from(html))
+            }
+            event => event,
+        })
+        .collect();
+
+    let mut output = String::new();
+    html::push_html(&mut output, events.into_iter());
+    if !order.is_empty() {
+        output.push_str(&footnotes_html(&order, &definitions, footnotes));
+    }
+    (output, changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKDOWN: &str =
+        "Second[^b] first[^a] again[^b].\n\n[^a]: Note *A*.\n\n[^b]: Note B.\n\n    More B.\n";
+
+    #[test]
+    fn test_render_markdown_end_notes() {
+        let html = render_markdown(
+            MARKDOWN,
+            FootnoteStyle::End,
+            Highlighting::Prism,
+            RawHtml::Allow,
+        )
+        .0;
+
+        assert!(html.starts_with(
+            r##"<p>Second<span class="footnote"><sup class="footnote-ref" id="fnref-1"><a href="#fn-1">1</a></sup><span class="footnote-preview" role="tooltip">Note B.<br>More B.</span></span> first<span class="footnote"><sup class="footnote-ref" id="fnref-2"><a href="#fn-2">2</a></sup>"##
+        ));
+        // A repeated reference reuses the number without duplicating the id
+        assert!(html.contains(r##" again<span class="footnote"><sup class="footnote-ref"><a href="#fn-1">1</a></sup>"##));
+        assert!(html.contains("<section class=\"footnotes\">\n<ol>\n<li id=\"fn-1\">\n<p>Note B.</p>\n<p>More B.</p>\n<a href=\"#fnref-1\""));
+        assert!(html.contains("<li id=\"fn-2\">\n<p>Note <em>A</em>.</p>\n"));
+        assert!(!html.contains("footnote-definition"));
+    }
+
+    #[test]
+    fn test_render_markdown_sidenotes() {
+        let html = render_markdown(
+            MARKDOWN,
+            FootnoteStyle::Sidenotes,
+            Highlighting::Prism,
+            RawHtml::Allow,
+        )
+        .0;
+
+        assert!(html.contains(
+            r#"<span class="sidenote" role="note"><span class="sidenote-number">2</span> Note <em>A</em>.</span>"#
+        ));
+        assert!(html.contains("<section class=\"footnotes footnotes-sidenotes\">"));
+    }
+
+    #[test]
+    fn test_render_markdown_highlights_code_statically() {
+        let markdown = "```python\nprint(1)\n```\n\n    indented <code>\n";
+        let html = render_markdown(
+            markdown,
+            FootnoteStyle::End,
+            Highlighting::Static,
+            RawHtml::Allow,
+        )
+        .0;
+
+        assert!(html.starts_with("<div class=\"code-block\">\n<pre class=\"hl-code\"><code class=\"language-python\"><span class=\"hl-source hl-python\">"));
+        assert!(html.contains("<pre class=\"hl-code\"><code>indented &lt;code&gt;\n</code></pre>"));
+    }
+
+    #[test]
+    fn test_render_markdown_without_footnotes() {
+        assert_eq!(
+            render_markdown(
+                "Just [^text].",
+                FootnoteStyle::End,
+                Highlighting::Prism,
+                RawHtml::Allow
+            )
+            .0,
+            "<p>Just [^text].</p>\n"
+        );
+    }
+}
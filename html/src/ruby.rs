@@ -0,0 +1,201 @@
+use crate::escape::escape_html;
+use crate::prose::{link_ranges, map_prose_at};
+use crate::raw_html::generated;
+
+/// Characters that may form the base text of `漢字《かんじ》` without a leading `｜`.
+fn is_kanji(c: char) -> bool {
+    matches!(c,
+        '\u{4E00}'..='\u{9FFF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '々' | '〆' | 'ヶ' | '〇')
+}
+
+/// Parses `{base|reading}` at the start of `text`, returning the base, the reading and
+/// the length in bytes of the annotation.
+fn parse_braced(text: &str) -> Option<(&str, &str, usize)> {
+    let end = text.find('}')?;
+    let (base, reading) = text[1..end].split_once('|')?;
+    let valid = |part: &str| !part.trim().is_empty() && !part.contains(['{', '|']);
+    (valid(base) && valid(reading)).then_some((base, reading, end + 1))
+}
+
+/// Parses `《reading》` at the start of `text`, returning the reading and the length in
+/// bytes of the annotation.
+fn parse_reading(text: &str) -> Option<(&str, usize)> {
+    let inner = text.strip_prefix('《')?;
+    let end = inner.find('》')?;
+    let reading = &inner[..end];
+    (!reading.is_empty() && !reading.contains('《'))
+        .then_some((reading, '《'.len_utf8() + end + '》'.len_utf8()))
+}
+
+/// Finds every ruby annotation in a stretch of prose and replaces it with `render(base, reading)`.
+fn annotate(text: &str, render: &impl Fn(&str, &str) -> String) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(pos) = rest.find(['{', '｜', '《']) {
+        let (before, at) = rest.split_at(pos);
+        let escaped = before.ends_with('\\');
+        let marker = at.chars().next().unwrap_or_default();
+
+        let annotation = match marker {
+            '{' if !escaped => {
+                parse_braced(at).map(|(base, reading, len)| (before, base, reading, len))
+            }
+            '｜' => {
+                let bar = '｜'.len_utf8();
+                at[bar..].find('《').and_then(|base_len| {
+                    let base = &at[bar..bar + base_len];
+                    let (reading, len) = parse_reading(&at[bar + base_len..])?;
+                    (!base.is_empty() && !base.contains(['｜', '》'])).then_some((
+                        before,
+                        base,
+                        reading,
+                        bar + base_len + len,
+                    ))
+                })
+            }
+            '《' => {
+                // Without `｜`, the base is the run of kanji right before the reading
+                let base_start = before
+                    .char_indices()
+                    .rev()
+                    .take_while(|(_, c)| is_kanji(*c))
+                    .last()
+                    .map(|(i, _)| i);
+                base_start.and_then(|start| {
+                    let (reading, len) = parse_reading(at)?;
+                    Some((&before[..start], &before[start..], reading, len))
+                })
+            }
+            _ => None,
+        };
+
+        match annotation {
+            Some((prefix, base, reading, len)) => {
+                output.push_str(prefix);
+                output.push_str(&render(base, reading));
+                rest = &at[len..];
+            }
+            None => {
+                output.push_str(before);
+                output.push(marker);
+                rest = &at[marker.len_utf8()..];
+            }
+        }
+    }
+    output.push_str(rest);
+
+    output
+}
+
+fn ruby_html(base: &str, reading: &str) -> String {
+    generated(&format!(
+        "<ruby><rb>{base}</rb><rp>(</rp><rt>{reading}</rt><rp>)</rp></ruby>",
+        base = escape_html(base),
+        reading = escape_html(reading)
+    ))
+}
+
+fn base_text(base: &str, _reading: &str) -> String {
+    base.to_string()
+}
+
+/// Applies `render` to the annotations in the prose of `markdown`. Image alt texts only
+/// get the base text, since markup would show as text there, and link destinations are
+/// left alone.
+fn annotate_prose(markdown: &str, render: &impl Fn(&str, &str) -> String) -> String {
+    let links = link_ranges(markdown);
+    map_prose_at(markdown, |text, offset| {
+        let end = offset + text.len();
+        let mut skipped: Vec<(usize, usize, bool)> = links
+            .alt_texts
+            .iter()
+            .map(|range| (range, true))
+            .chain(links.destinations.iter().map(|range| (range, false)))
+            .filter(|(range, _)| range.start < end && range.end > offset)
+            .map(|(range, alt)| (range.start.max(offset), range.end.min(end), alt))
+            .collect();
+        skipped.sort();
+
+        let mut output = String::with_capacity(text.len());
+        let mut last = offset;
+        for (skip_start, skip_end, alt) in skipped {
+            output.push_str(&annotate(&markdown[last..skip_start], render));
+            let part = &markdown[skip_start..skip_end];
+            if alt {
+                output.push_str(&annotate(part, &base_text));
+            } else {
+                output.push_str(part);
+            }
+            last = skip_end;
+        }
+        output.push_str(&annotate(&markdown[last..end], render));
+        output
+    })
+}
+
+/// Renders ruby (furigana) annotations as `<ruby>` markup.
+///
+/// Both the `{漢字|かんじ}` style and the Aozora Bunko style `｜漢字《かんじ》` are
+/// supported; in the latter the `｜` may be omitted when the base text is all kanji
+/// (`漢字《かんじ》`). Annotations inside code and link destinations are left alone,
+/// image alt texts get only the base text, and `\{` keeps a brace literal.
+pub fn render_ruby(markdown: &str) -> String {
+    annotate_prose(markdown, &ruby_html)
+}
+
+/// Replaces ruby annotations with their base text, e.g. for search indexing where
+/// the readings would only add noise.
+pub fn strip_ruby(markdown: &str) -> String {
+    annotate_prose(markdown, &base_text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_html::without_markers;
+
+    #[test]
+    fn test_render_ruby() {
+        assert_eq!(
+            without_markers(&render_ruby("{漢字|かんじ}を読む")),
+            "<ruby><rb>漢字</rb><rp>(</rp><rt>かんじ</rt><rp>)</rp></ruby>を読む"
+        );
+        assert_eq!(
+            without_markers(&render_ruby("これは｜東京タワー《とうきょうタワー》です")),
+            "これは<ruby><rb>東京タワー</rb><rp>(</rp><rt>とうきょうタワー</rt><rp>)</rp></ruby>です"
+        );
+        // Without `｜` the base is the whole run of kanji before the reading
+        assert_eq!(
+            without_markers(&render_ruby("これは東京《とうきょう》")),
+            "これは<ruby><rb>東京</rb><rp>(</rp><rt>とうきょう</rt><rp>)</rp></ruby>"
+        );
+    }
+
+    #[test]
+    fn test_render_ruby_leaves_code_and_non_annotations_alone() {
+        let markdown = "`{漢字|かんじ}` \\{a|b} {a} 《本》\n```rust\nlet f = {x|y};\n```\n\n    let f = {x|y};\n\n````\n```\n{漢字|かんじ}\n```\n````";
+        assert_eq!(render_ruby(markdown), markdown);
+    }
+
+    #[test]
+    fn test_render_ruby_in_links_and_images() {
+        let markdown = "![{漢字|かんじ}の図](x.png) [{本|ほん}](https://example.com/{a|b})";
+
+        assert_eq!(
+            without_markers(&render_ruby(markdown)),
+            "![漢字の図](x.png) [<ruby><rb>本</rb><rp>(</rp><rt>ほん</rt><rp>)</rp></ruby>](https://example.com/{a|b})"
+        );
+    }
+
+    #[test]
+    fn test_strip_ruby() {
+        assert_eq!(
+            strip_ruby("{漢字|かんじ}と｜振り仮名《ふりがな》と本《ほん》"),
+            "漢字と振り仮名と本"
+        );
+    }
+}
@@ -0,0 +1,368 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::code_block::infer_language_from_path;
+use crate::code_fence::fence_for;
+use crate::diagnostic::Diagnostic;
+use crate::directive::{IncludeContext, missing_include_html, read_include};
+use crate::options::RunOptions;
+use crate::raw_html::generated;
+
+/// Output captured from running a script.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct RunOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// Exit code, or `None` if the process was killed by a signal.
+    pub exit_code: Option<i32>,
+}
+
+/// Reads all of a child's pipe on a separate thread so a chatty process cannot block on a full pipe.
+fn read_pipe(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        String::from_utf8_lossy(&buf).to_string()
+    })
+}
+
+/// Runs `script` with `interpreter` (a command line such as `python3 -u`) from `work_dir`,
+/// killing it if it runs longer than `timeout`.
+pub fn run_script(
+    interpreter: &str,
+    script: &Path,
+    work_dir: &Path,
+    timeout: Duration,
+) -> Result<RunOutput, String> {
+    let mut parts = interpreter.split_whitespace();
+    let program = parts.next().ok_or("empty interpreter command")?;
+
+    let mut child = Command::new(program)
+        .args(parts)
+        .arg(script)
+        .current_dir(work_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("could not start `{program}`: {e}"))?;
+
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("timed out after {}s", timeout.as_secs_f32()));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+
+    Ok(RunOutput {
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+        exit_code: status.code(),
+    })
+}
+
+/// Hashes the files directly in `dir` that a script may read, by name and content.
+/// Subdirectories, hidden files and Markdown posts are left out, so that editing a post
+/// or an unrelated asset elsewhere in the posts tree does not re-run its scripts.
+fn hash_inputs(hasher: &mut Sha256, dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "md")
+            && let Ok(content) = fs::read(&path)
+        {
+            hasher.update(entry.file_name().as_encoded_bytes());
+            hasher.update([0]);
+            hasher.update(Sha256::digest(&content));
+        }
+    }
+}
+
+/// Cache key for a script run: changes whenever the interpreter, the script or a file
+/// next to the script changes.
+fn cache_key(interpreter: &str, script_path: &Path, script: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(interpreter.as_bytes());
+    hasher.update([0]);
+    hasher.update(script.as_bytes());
+    hasher.update([0]);
+    if let Some(dir) = script_path.parent() {
+        hash_inputs(&mut hasher, dir);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Runs the script, or returns the cached output of a previous run with the same
+/// script and inputs. Only successful runs are cached.
+fn run_cached(
+    interpreter: &str,
+    script_path: &Path,
+    script: &str,
+    work_dir: &Path,
+    options: &RunOptions,
+) -> Result<RunOutput, String> {
+    let cache_path = options.cache_dir.as_deref().map(|cache_dir| {
+        let key = cache_key(interpreter, script_path, script);
+        cache_dir.join("run").join(format!("{key}.json"))
+    });
+
+    if let Some(cached) = cache_path
+        .as_deref()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+    {
+        return Ok(cached);
+    }
+
+    let output = run_script(interpreter, script_path, work_dir, options.timeout)?;
+
+    if let Some(cache_path) = cache_path
+        && output.exit_code == Some(0)
+    {
+        // A failed cache write only costs a re-run next time
+        if let Some(parent) = cache_path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string(&output) {
+            let _ = fs::write(cache_path, json);
+        }
+    }
+
+    Ok(output)
+}
+
+fn output_block(class: &str, text: &str) -> String {
+    let text = text.trim_end_matches('\n');
+    if text.is_empty() {
+        return String::new();
+    }
+    let fence = fence_for(text);
+    format!(
+        "{}\n\n{fence}text\n{text}\n{fence}\n\n</div>\n",
+        generated(&format!("<div class=\"{class}\">"))
+    )
+}
+
+/// Expands a single `@run[script.py]` directive.
+///
+/// The script is resolved like `@code[...]`, run from the directory of the post with the
+/// interpreter configured for its extension, and rendered as a code block followed by
+/// its captured stdout and stderr. Running requires `run.interpreters` in `mejiro.yml`;
+/// otherwise the directive is reported and replaced with a placeholder.
+pub(crate) fn expand_run_include(
+    arg: &str,
+    line: usize,
+    ctx: &IncludeContext,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> String {
+    let file_path = arg.trim();
+
+    let Some(options) = &ctx.options.run else {
+        diagnostics.push(Diagnostic::warning(
+            ctx.path,
+            line,
+            "`@run` is disabled; add `run.interpreters` to mejiro.yml to enable it",
+        ));
+        return missing_include_html("run", file_path);
+    };
+
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+    let Some(interpreter) = options.interpreters.get(extension) else {
+        diagnostics.push(Diagnostic::warning(
+            ctx.path,
+            line,
+            format!("`@run[{file_path}]`: no interpreter configured for `.{extension}` files"),
+        ));
+        return missing_include_html("run", file_path);
+    };
+
+    let Some((script_path, script)) = read_include("run", file_path, line, ctx, diagnostics) else {
+        return missing_include_html("run", file_path);
+    };
+
+    let lang = infer_language_from_path(file_path);
+    let code = script.trim_end_matches('\n');
+    let fence = fence_for(code);
+    let source = format!("{fence}{lang}\n{code}\n{fence}\n\n");
+
+    match run_cached(interpreter, &script_path, &script, ctx.base_dir(), options) {
+        Ok(output) => {
+            if output.exit_code != Some(0) {
+                let status = output
+                    .exit_code
+                    .map_or_else(|| "a signal".to_string(), |code| format!("code {code}"));
+                diagnostics.push(Diagnostic::warning(
+                    ctx.path,
+                    line,
+                    format!("`@run[{file_path}]` exited with {status}"),
+                ));
+            }
+            source
+                + &output_block("run-output", &output.stdout)
+                + &output_block("run-output run-stderr", &output.stderr)
+        }
+        Err(e) => {
+            diagnostics.push(Diagnostic::warning(
+                ctx.path,
+                line,
+                format!("`@run[{file_path}]` failed: {e}"),
+            ));
+            source + &missing_include_html("run", file_path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::RenderOptions;
+    use crate::raw_html::without_markers;
+    use crate::test_fixtures::preprocess;
+    use std::collections::BTreeMap;
+    use tempfile::TempDir;
+
+    fn run_options(cache_dir: Option<&Path>, timeout: Duration) -> RenderOptions {
+        RenderOptions {
+            run: Some(RunOptions {
+                interpreters: BTreeMap::from([("sh".to_string(), "sh".to_string())]),
+                timeout,
+                cache_dir: cache_dir.map(Path::to_path_buf),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_run_embeds_source_and_output() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("data.txt"), "from the post dir").unwrap();
+        fs::write(
+            temp_dir.path().join("hello.sh"),
+            "cat data.txt\necho oops >&2\n",
+        )
+        .unwrap();
+
+        let options = run_options(None, Duration::from_secs(10));
+        let (result, diagnostics) = preprocess("@run[hello.sh]", temp_dir.path(), &options);
+        let result = without_markers(&result);
+
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert!(result.contains("```bash\ncat data.txt\necho oops >&2\n```"));
+        assert!(result.contains("<div class=\"run-output\">\n\n```text\nfrom the post dir\n```"));
+        assert!(result.contains("<div class=\"run-output run-stderr\">\n\n```text\noops\n```"));
+    }
+
+    #[test]
+    fn test_run_uses_cache_for_unchanged_script() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path().join(".cache");
+        let script = temp_dir.path().join("count.sh");
+        fs::write(&script, "echo run >> .runs.log\ncat .runs.log | wc -l\n").unwrap();
+
+        let options = run_options(Some(&cache_dir), Duration::from_secs(10));
+        let (first, _) = preprocess("@run[count.sh]", temp_dir.path(), &options);
+        let (second, _) = preprocess("@run[count.sh]", temp_dir.path(), &options);
+
+        assert_eq!(first, second);
+        let runs = fs::read_to_string(temp_dir.path().join(".runs.log")).unwrap();
+        assert_eq!(runs.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_run_cache_tracks_files_next_to_script() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path().join(".cache");
+        let script_dir = temp_dir.path().join("scripts");
+        let other_dir = temp_dir.path().join("assets");
+        fs::create_dir_all(&script_dir).unwrap();
+        fs::create_dir_all(&other_dir).unwrap();
+        fs::write(script_dir.join("cat.sh"), "cat scripts/data.txt\n").unwrap();
+        fs::write(script_dir.join("data.txt"), "old").unwrap();
+
+        let options = run_options(Some(&cache_dir), Duration::from_secs(10));
+        let run = || preprocess("@run[scripts/cat.sh]", temp_dir.path(), &options).0;
+        let first = run();
+        fs::write(temp_dir.path().join("post.md"), "edited").unwrap();
+        fs::write(temp_dir.path().join("image.png"), "unrelated").unwrap();
+        fs::write(other_dir.join("image.png"), "unrelated").unwrap();
+        let second = run();
+        fs::write(script_dir.join("data.txt"), "new").unwrap();
+        let third = run();
+
+        assert_eq!(first, second);
+        assert!(first.contains("```text\nold\n```"));
+        assert!(third.contains("```text\nnew\n```"));
+    }
+
+    #[test]
+    fn test_run_does_not_cache_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path().join(".cache");
+        fs::write(
+            temp_dir.path().join("fail.sh"),
+            "echo run >> .runs.log\nexit 1\n",
+        )
+        .unwrap();
+
+        let options = run_options(Some(&cache_dir), Duration::from_secs(10));
+        preprocess("@run[fail.sh]", temp_dir.path(), &options);
+        preprocess("@run[fail.sh]", temp_dir.path(), &options);
+
+        let runs = fs::read_to_string(temp_dir.path().join(".runs.log")).unwrap();
+        assert_eq!(runs.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_run_times_out() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("slow.sh"), "sleep 5\n").unwrap();
+
+        let options = run_options(None, Duration::from_millis(100));
+        let (result, diagnostics) = preprocess("@run[slow.sh]", temp_dir.path(), &options);
+
+        assert!(result.contains("include-missing"));
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("timed out"));
+    }
+
+    #[test]
+    fn test_run_is_disabled_by_default() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("hello.sh"), "echo hi\n").unwrap();
+
+        let (result, diagnostics) =
+            preprocess("@run[hello.sh]", temp_dir.path(), &RenderOptions::default());
+
+        assert!(!result.contains("echo hi"));
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.contains("disabled"));
+    }
+}
@@ -0,0 +1,363 @@
+use base64::prelude::*;
+use sha2::{Digest, Sha256, Sha384};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+use crate::code_fence::CODE_COPY_SCRIPT;
+use crate::code_tabs::CODE_TABS_SCRIPT;
+use crate::color_scheme::COLOR_SCHEME_SCRIPT;
+use crate::escape::escape_html;
+use crate::highlight::{prism_init_script, prism_urls};
+use crate::index::search_script;
+use crate::raw_html::{Attribute, parse_attributes, tag_end};
+
+/// A `<script>` or `<link>` tag found in a page.
+struct Element<'a> {
+    name: &'static str,
+    /// Span of the start tag in the page.
+    tag: Range<usize>,
+    attributes: Vec<Attribute<'a>>,
+    /// Content between the start and end tag of a `<script>`.
+    content: Option<&'a str>,
+}
+
+impl Element<'_> {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .map(|attribute| attribute.value)
+    }
+
+    fn is_stylesheet(&self) -> bool {
+        self.name == "link"
+            && self
+                .attribute("rel")
+                .is_some_and(|rel| rel.split_whitespace().any(|r| r == "stylesheet"))
+    }
+
+    /// The URL of an external script or stylesheet.
+    fn external_url(&self) -> Option<&str> {
+        let url = match self.name {
+            "script" => self.attribute("src"),
+            _ if self.is_stylesheet() => self.attribute("href"),
+            _ => None,
+        }?;
+        (url.starts_with("https://") || url.starts_with("http://")).then_some(url)
+    }
+}
+
+/// Finds every `<name ...>` tag of a page, with the content up to `</name>` for scripts.
+fn elements<'a>(html: &'a str, name: &'static str) -> Vec<Element<'a>> {
+    let open = format!("<{name}");
+    let mut found = Vec::new();
+    let mut search = 0;
+    while let Some(i) = html[search..].find(&open) {
+        let start = search + i;
+        let after = html[start + open.len()..].chars().next();
+        search = start + open.len();
+        if !after.is_some_and(|c| c.is_whitespace() || c == '>' || c == '/') {
+            continue;
+        }
+        let Some(len) = tag_end(&html[start..]) else {
+            break;
+        };
+        let end = start + len + 1;
+        let attributes = parse_attributes(&html[start..end], open.len())
+            .into_iter()
+            .map(|attribute| Attribute {
+                span: attribute.span.start + start..attribute.span.end + start,
+                ..attribute
+            })
+            .collect();
+        let content = (name == "script").then(|| {
+            let close = html[end..].find("</script").map_or(html.len(), |i| end + i);
+            &html[end..close]
+        });
+        found.push(Element {
+            name,
+            tag: start..end,
+            attributes,
+            content,
+        });
+        search = content.map_or(end, |content| end + content.len());
+    }
+    found
+}
+
+/// Returns the URLs of the external scripts and stylesheets a page loads.
+pub fn external_assets(html: &str) -> BTreeSet<String> {
+    elements(html, "script")
+        .into_iter()
+        .chain(elements(html, "link"))
+        .filter_map(|element| element.external_url().map(str::to_string))
+        .collect()
+}
+
+/// Returns the Subresource Integrity value of `bytes`, e.g. `sha384-...`.
+pub fn integrity(bytes: &[u8]) -> String {
+    format!("sha384-{}", BASE64_STANDARD.encode(Sha384::digest(bytes)))
+}
+
+/// Adds `integrity` and `crossorigin` attributes to the external scripts and
+/// stylesheets whose URL has an entry in `integrities`. Tags that already have an
+/// `integrity` attribute are left alone.
+pub fn add_integrity(html: &str, integrities: &BTreeMap<String, String>) -> String {
+    let mut insertions: Vec<(usize, String)> = elements(html, "script")
+        .into_iter()
+        .chain(elements(html, "link"))
+        .filter(|element| element.attribute("integrity").is_none())
+        .filter_map(|element| {
+            let integrity = integrities.get(element.external_url()?)?;
+            let tag = &html[element.tag.clone()];
+            let end = element.tag.end - if tag.ends_with("/>") { 2 } else { 1 };
+            let at = html[..end].trim_end().len();
+            Some((
+                at,
+                format!(
+                    r#" integrity="{}" crossorigin="anonymous""#,
+                    escape_html(integrity)
+                ),
+            ))
+        })
+        .collect();
+    insertions.sort();
+
+    let mut output = String::with_capacity(html.len());
+    let mut last = 0;
+    for (at, attributes) in insertions {
+        output.push_str(&html[last..at]);
+        output.push_str(&attributes);
+        last = at;
+    }
+    output.push_str(&html[last..]);
+    output
+}
+
+/// `scheme://host[:port]` of an absolute URL, as used in a CSP source list.
+fn origin(url: &str) -> Option<&str> {
+    let scheme_end = url.find("://")? + 3;
+    let end = url[scheme_end..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |i| scheme_end + i);
+    Some(&url[..end])
+}
+
+fn sha256_source(content: &str) -> String {
+    format!(
+        "'sha256-{}'",
+        BASE64_STANDARD.encode(Sha256::digest(content.as_bytes()))
+    )
+}
+
+/// The inline scripts that mejiro generates. They are the only inline scripts a
+/// policy allows, so scripts from a post's raw HTML never run.
+fn mejiro_scripts() -> [String; 5] {
+    [
+        COLOR_SCHEME_SCRIPT.to_string(),
+        CODE_TABS_SCRIPT.to_string(),
+        CODE_COPY_SCRIPT.to_string(),
+        prism_init_script(),
+        search_script(),
+    ]
+}
+
+/// Computes a Content Security Policy for a page: its own origin, the CDN origins of
+/// the mejiro assets it loads, the origins of its images, and mejiro's inline scripts
+/// that it contains, by hash. Other scripts and stylesheets, such as ones added with
+/// raw HTML in a post, are blocked, and so are event handler attributes.
+pub fn content_security_policy(html: &str) -> String {
+    let mut scripts = BTreeSet::from(["'self'".to_string()]);
+    let mut styles = BTreeSet::from(["'self'".to_string()]);
+    let mut fonts = BTreeSet::from(["'self'".to_string()]);
+    let mut images = BTreeSet::from(["'self'".to_string(), "data:".to_string()]);
+
+    let known_scripts = mejiro_scripts();
+    let known_assets = prism_urls();
+    let is_known_asset = |url: &str| known_assets.iter().any(|known| known == url);
+    for script in elements(html, "script") {
+        match (script.external_url(), script.content) {
+            (Some(url), _) if is_known_asset(url) => {
+                if let Some(origin) = origin(url) {
+                    scripts.insert(origin.to_string());
+                }
+            }
+            (None, Some(content))
+                if script.attribute("src").is_none()
+                    && known_scripts.contains(&content.to_string()) =>
+            {
+                scripts.insert(sha256_source(content));
+                // The search index is compiled to WebAssembly
+                if content.contains("mejiro-search-pkg/") {
+                    scripts.insert("'wasm-unsafe-eval'".to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for link in elements(html, "link") {
+        // Fonts are typically loaded by the stylesheets
+        if let Some(url) = link.external_url().filter(|url| is_known_asset(url))
+            && let Some(origin) = origin(url)
+        {
+            styles.insert(origin.to_string());
+            fonts.insert(origin.to_string());
+        }
+    }
+    if html.contains(" style=\"") {
+        styles.insert("'unsafe-inline'".to_string());
+    }
+
+    for img in elements(html, "img") {
+        if let Some(origin) = img.attribute("src").and_then(origin) {
+            images.insert(origin.to_string());
+        }
+    }
+
+    let join = |sources: BTreeSet<String>| sources.into_iter().collect::<Vec<_>>().join(" ");
+    format!(
+        "default-src 'self'; script-src {scripts}; style-src {styles}; img-src {images}; font-src {fonts}; object-src 'none'; base-uri 'self'; form-action 'self'",
+        scripts = join(scripts),
+        styles = join(styles),
+        images = join(images),
+        fonts = join(fonts),
+    )
+}
+
+/// Inserts a `<meta http-equiv="Content-Security-Policy">` tag at the start of `<head>`
+/// (after `<meta charset>`), so that it applies to everything the page loads.
+pub fn insert_csp_meta(html: &str, policy: &str) -> String {
+    let meta = format!(
+        r#"<meta http-equiv="Content-Security-Policy" content="{}" />"#,
+        escape_html(policy)
+    );
+    let at = match html.find("<meta charset") {
+        Some(start) => html[start..].find('>').map(|i| start + i + 1),
+        None => html.find("<head>").map(|start| start + "<head>".len()),
+    };
+    match at {
+        Some(at) => format!("{}\n  {meta}{}", &html[..at], &html[at..]),
+        None => html.to_string(),
+    }
+}
+
+/// Hardens a generated page: adds the hashes in `integrities` to the assets it loads,
+/// then, if `csp` is set, a Content Security Policy computed from the result.
+pub fn secure_page(html: &str, integrities: &BTreeMap<String, String>, csp: bool) -> String {
+    let html = add_integrity(html, integrities);
+    if !csp {
+        return html;
+    }
+    let policy = content_security_policy(&html);
+    insert_csp_meta(&html, &policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::highlight::PRISM_CDN;
+
+    /// A page with mejiro's own assets, followed by a post body that adds its own.
+    fn page() -> String {
+        format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="UTF-8" />
+  <link rel="stylesheet" href="style.css">
+  <link rel="stylesheet" href="{PRISM_CDN}/themes/prism.min.css" media="all">
+  <script src="{PRISM_CDN}/components/prism-core.min.js"></script>
+  <script>{COLOR_SCHEME_SCRIPT}</script>
+</head>
+<body><img src="https://images.example.org/a.png">
+<script src="https://evil.example/x.js"></script>
+<script>alert(1)</script>
+<link rel="stylesheet" href="https://evil.example/x.css">
+</body>
+</html>"#
+        )
+    }
+
+    #[test]
+    fn test_add_integrity() {
+        let page = page();
+        let theme = format!("{PRISM_CDN}/themes/prism.min.css");
+        let core = format!("{PRISM_CDN}/components/prism-core.min.js");
+        assert_eq!(
+            external_assets(&page),
+            BTreeSet::from([
+                core.clone(),
+                theme.clone(),
+                "https://evil.example/x.css".to_string(),
+                "https://evil.example/x.js".to_string(),
+            ])
+        );
+
+        let integrities = BTreeMap::from([
+            (core.clone(), integrity(b"core")),
+            (theme.clone(), "sha384-x".to_string()),
+        ]);
+        let html = add_integrity(&page, &integrities);
+
+        assert!(html.contains(&format!(
+            r#"<link rel="stylesheet" href="{theme}" media="all" integrity="sha384-x" crossorigin="anonymous">"#
+        )));
+        assert!(html.contains(&format!(
+            r#"<script src="{core}" integrity="{}" crossorigin="anonymous"></script>"#,
+            integrity(b"core")
+        )));
+        assert!(html.contains(r#"<link rel="stylesheet" href="style.css">"#));
+        assert!(html.contains(r#"<script src="https://evil.example/x.js"></script>"#));
+    }
+
+    #[test]
+    fn test_content_security_policy() {
+        let policy = content_security_policy(&page());
+
+        assert_eq!(
+            policy,
+            format!(
+                "default-src 'self'; script-src 'self' {color_scheme} https://cdn.jsdelivr.net; style-src 'self' https://cdn.jsdelivr.net; img-src 'self' data: https://images.example.org; font-src 'self' https://cdn.jsdelivr.net; object-src 'none'; base-uri 'self'; form-action 'self'",
+                color_scheme = sha256_source(COLOR_SCHEME_SCRIPT)
+            )
+        );
+
+        let html = insert_csp_meta(&page(), &policy);
+        assert!(html.contains("<meta charset=\"UTF-8\" />\n  <meta http-equiv=\"Content-Security-Policy\" content=\"default-src &#39;self&#39;;"));
+    }
+
+    #[test]
+    fn test_content_security_policy_blocks_raw_html_scripts() {
+        let policy = content_security_policy(&page());
+
+        assert!(!policy.contains("evil.example"));
+        assert!(!policy.contains(&sha256_source("alert(1)")));
+        assert!(!policy.contains("'unsafe-inline'"));
+
+        let index = format!(r#"<script type="module">{}</script>"#, search_script());
+        let policy = content_security_policy(&index);
+        assert!(policy.contains(&sha256_source(&search_script())));
+        assert!(policy.contains("'wasm-unsafe-eval'"));
+        let policy = content_security_policy("<p>mejiro-search-pkg/</p>");
+        assert!(!policy.contains("'wasm-unsafe-eval'"));
+    }
+
+    #[test]
+    fn test_secure_page() {
+        let page = page();
+        let core = format!("{PRISM_CDN}/components/prism-core.min.js");
+        let integrities = BTreeMap::from([(core.clone(), "sha384-x".to_string())]);
+
+        let html = secure_page(&page, &integrities, false);
+        assert!(html.contains(&format!(
+            r#"<script src="{core}" integrity="sha384-x" crossorigin="anonymous"></script>"#
+        )));
+        assert!(html.contains(r#"themes/prism.min.css" media="all">"#));
+        assert!(!html.contains("Content-Security-Policy"));
+
+        let html = secure_page(&page, &BTreeMap::new(), true);
+        assert!(!html.contains("integrity="));
+        assert!(html.contains("<meta http-equiv=\"Content-Security-Policy\""));
+    }
+}
@@ -0,0 +1,178 @@
+use crate::escape::escape_html;
+use crate::metadata::Post;
+
+/// Resolves `path`, relative to the site directory `dir` (e.g. `posts/`), into an
+/// absolute URL. Full URLs are returned as-is. Without a site URL, only full URLs
+/// can be resolved.
+pub(crate) fn absolute_url(site_url: Option<&str>, dir: &str, path: &str) -> Option<String> {
+    if path.starts_with("https://") || path.starts_with("http://") {
+        return Some(path.to_string());
+    }
+    let site_url = site_url?.trim_end_matches('/');
+    let (mut segments, path) = match path.strip_prefix('/') {
+        Some(path) => (Vec::new(), path),
+        None => (dir.split('/').filter(|s| !s.is_empty()).collect(), path),
+    };
+    for segment in path.split('/') {
+        match segment {
+            ".." => {
+                segments.pop();
+            }
+            "." | "" => {}
+            segment => segments.push(segment),
+        }
+    }
+    Some(format!("{site_url}/{}", segments.join("/")))
+}
+
+/// The absolute URL of the index page.
+pub(crate) fn home_url(site_url: Option<&str>) -> Option<String> {
+    site_url.map(|url| format!("{}/", url.trim_end_matches('/')))
+}
+
+/// The absolute URL of a post page.
+pub(crate) fn post_url(post: &Post, site_url: Option<&str>) -> Option<String> {
+    absolute_url(site_url, "posts/", &format!("{}.html", post.name))
+}
+
+/// The absolute URL of a post's image, or of `default_image` for posts without one.
+pub(crate) fn post_image_url(
+    post: &Post,
+    site_url: Option<&str>,
+    default_image: Option<&str>,
+) -> Option<String> {
+    match &post.meta.image {
+        Some(image) => absolute_url(site_url, "posts/", image),
+        None => default_image.and_then(|image| absolute_url(site_url, "", image)),
+    }
+}
+
+fn meta_property(property: &str, content: &str) -> String {
+    format!(
+        r#"<meta property="{property}" content="{}" />"#,
+        escape_html(content)
+    )
+}
+
+/// The canonical link, `og:url`, `og:image` and `twitter:card` tags shared by every page.
+fn page_tags(url: Option<&str>, image: Option<&str>) -> Vec<String> {
+    let mut tags = Vec::new();
+    if let Some(url) = url {
+        tags.push(format!(
+            r#"<link rel="canonical" href="{}">"#,
+            escape_html(url)
+        ));
+        tags.push(meta_property("og:url", url));
+    }
+    if let Some(image) = image {
+        tags.push(meta_property("og:image", image));
+    }
+    let card = if image.is_some() {
+        "summary_large_image"
+    } else {
+        "summary"
+    };
+    tags.push(format!(r#"<meta name="twitter:card" content="{card}" />"#));
+    tags
+}
+
+/// Social media meta tags for a post page, added to what `post_html` already emits.
+/// `default_image` is used for posts without an `image` in their front matter and is
+/// relative to the site root. Relative URLs need `site_url` to be resolved and are
+/// left out otherwise.
+pub fn post_social_html(
+    post: &Post,
+    site_url: Option<&str>,
+    default_image: Option<&str>,
+) -> String {
+    let url = post
+        .meta
+        .canonical_url
+        .clone()
+        .or_else(|| post_url(post, site_url));
+    let image = post_image_url(post, site_url, default_image);
+
+    let mut tags = page_tags(url.as_deref(), image.as_deref());
+    tags.push(meta_property(
+        "article:published_time",
+        &post.meta.published_at,
+    ));
+    for topic in &post.meta.topics {
+        tags.push(meta_property("article:tag", topic));
+    }
+    tags.join("\n  ")
+}
+
+/// Social media meta tags for the index page.
+pub fn index_social_html(
+    site_title: &str,
+    site_url: Option<&str>,
+    default_image: Option<&str>,
+) -> String {
+    let url = home_url(site_url);
+    let image = default_image.and_then(|image| absolute_url(site_url, "", image));
+
+    let mut tags = vec![
+        meta_property("og:title", site_title),
+        meta_property("og:type", "website"),
+        meta_property("og:site_name", site_title),
+    ];
+    tags.extend(page_tags(url.as_deref(), image.as_deref()));
+    tags.join("\n  ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::post_from;
+
+    #[test]
+    fn test_absolute_url() {
+        let site = Some("https://example.com/blog/");
+        assert_eq!(
+            absolute_url(site, "posts/", "../images/a.png").as_deref(),
+            Some("https://example.com/blog/images/a.png")
+        );
+        assert_eq!(
+            absolute_url(site, "posts/", "/card.png").as_deref(),
+            Some("https://example.com/blog/card.png")
+        );
+        assert_eq!(
+            absolute_url(None, "posts/", "https://cdn.example.com/a.png").as_deref(),
+            Some("https://cdn.example.com/a.png")
+        );
+        assert_eq!(absolute_url(None, "posts/", "../images/a.png"), None);
+    }
+
+    #[test]
+    fn test_post_social_html() {
+        let post = post_from(
+            "---\ntitle: Hello\ntopics: [rust, 'a&b']\npublished: true\npublished_at: 2024-01-02\ntldr: null\ncover: ../images/cover.png\n---\n",
+        );
+        let html = post_social_html(&post, Some("https://example.com"), Some("card.png"));
+
+        assert!(
+            html.contains(r#"<link rel="canonical" href="https://example.com/posts/hello.html">"#)
+        );
+        assert!(html.contains(
+            r#"<meta property="og:url" content="https://example.com/posts/hello.html" />"#
+        ));
+        assert!(html.contains(
+            r#"<meta property="og:image" content="https://example.com/images/cover.png" />"#
+        ));
+        assert!(html.contains(r#"<meta name="twitter:card" content="summary_large_image" />"#));
+        assert!(
+            html.contains(r#"<meta property="article:published_time" content="2024-01-02" />"#)
+        );
+        assert!(html.contains(r#"<meta property="article:tag" content="a&amp;b" />"#));
+
+        let post = post_from(
+            "---\ntitle: Hello\ntopics: []\npublished: true\npublished_at: 2024-01-02\ntldr: null\ncanonical_url: https://dev.to/me/hello\n---\n",
+        );
+        let html = post_social_html(&post, None, Some("card.png"));
+
+        assert!(html.contains(r#"<link rel="canonical" href="https://dev.to/me/hello">"#));
+        assert!(!html.contains("og:image"));
+        assert!(html.contains(r#"<meta name="twitter:card" content="summary" />"#));
+    }
+}
@@ -0,0 +1,213 @@
+use serde_json::{Map, Value, json};
+
+use crate::metadata::Post;
+use crate::social::{home_url, post_image_url, post_url};
+
+/// The author of the blog, as described in structured data.
+pub struct Author<'a> {
+    pub name: &'a str,
+    /// Profiles of the author, such as GitHub and LinkedIn.
+    pub links: Vec<&'a str>,
+}
+
+impl Author<'_> {
+    fn to_json(&self) -> Value {
+        let mut person = json!({ "@type": "Person", "name": self.name });
+        let links: Vec<&str> = self
+            .links
+            .iter()
+            .copied()
+            .filter(|link| !link.is_empty())
+            .collect();
+        if !links.is_empty() {
+            person["sameAs"] = json!(links);
+        }
+        person
+    }
+}
+
+/// Adds `key` to a JSON object if there is a value for it.
+fn insert_some(object: &mut Value, key: &str, value: Option<impl Into<Value>>) {
+    if let (Value::Object(object), Some(value)) = (object, value) {
+        object.insert(key.to_string(), value.into());
+    }
+}
+
+/// Wraps structured data in a `<script type="application/ld+json">` tag. `<` is
+/// escaped so that strings like `</script>` cannot end the tag early.
+fn json_ld_html(data: &Value) -> String {
+    let json = data.to_string().replace('<', "\\u003c");
+    format!(r#"<script type="application/ld+json">{json}</script>"#)
+}
+
+/// `BlogPosting` structured data for a post page. Absolute URLs are only included
+/// when `site_url` is known.
+pub fn post_json_ld_html(
+    post: &Post,
+    site_title: &str,
+    author: &Author,
+    site_url: Option<&str>,
+    default_image: Option<&str>,
+) -> String {
+    let mut posting = json!({
+        "@context": "https://schema.org",
+        "@type": "BlogPosting",
+        "headline": post.meta.title,
+        "datePublished": post.meta.published_at,
+        "author": author.to_json(),
+    });
+    insert_some(&mut posting, "description", post.meta.tldr.as_deref());
+    if !post.meta.topics.is_empty() {
+        posting["keywords"] = json!(post.meta.topics);
+    }
+    insert_some(
+        &mut posting,
+        "image",
+        post_image_url(post, site_url, default_image),
+    );
+    let url = post_url(post, site_url);
+    insert_some(&mut posting, "url", url.clone());
+    insert_some(
+        &mut posting,
+        "mainEntityOfPage",
+        post.meta.canonical_url.clone().or(url),
+    );
+
+    let mut blog = json!({ "@type": "Blog", "name": site_title });
+    insert_some(&mut blog, "url", home_url(site_url));
+    posting["isPartOf"] = blog;
+
+    json_ld_html(&posting)
+}
+
+/// `WebSite` and `Blog` structured data for the index page. The site search is
+/// described as a `SearchAction` when `site_url` is known.
+pub fn index_json_ld_html(
+    site_title: &str,
+    author: &Author,
+    posts: &[Post],
+    site_url: Option<&str>,
+) -> String {
+    let url = home_url(site_url);
+
+    let mut website = json!({ "@type": "WebSite", "name": site_title });
+    insert_some(&mut website, "url", url.clone());
+    if let Some(url) = &url {
+        website["potentialAction"] = json!({
+            "@type": "SearchAction",
+            "target": {
+                "@type": "EntryPoint",
+                "urlTemplate": format!("{url}?q={{search_term_string}}"),
+            },
+            "query-input": "required name=search_term_string",
+        });
+    }
+
+    let blog_posts: Vec<Value> = posts
+        .iter()
+        .map(|post| {
+            let mut posting = json!({
+                "@type": "BlogPosting",
+                "headline": post.meta.title,
+                "datePublished": post.meta.published_at,
+            });
+            insert_some(&mut posting, "url", post_url(post, site_url));
+            posting
+        })
+        .collect();
+    let mut blog = json!({
+        "@type": "Blog",
+        "name": site_title,
+        "author": author.to_json(),
+        "blogPost": blog_posts,
+    });
+    insert_some(&mut blog, "url", url);
+
+    let mut graph = Map::new();
+    graph.insert("@context".to_string(), json!("https://schema.org"));
+    graph.insert("@graph".to_string(), json!([website, blog]));
+    json_ld_html(&Value::Object(graph))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_fixtures::post_from;
+
+    fn parse_json_ld(html: &str) -> Value {
+        let json = html
+            .strip_prefix(r#"<script type="application/ld+json">"#)
+            .and_then(|html| html.strip_suffix("</script>"))
+            .unwrap();
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_post_json_ld_html() {
+        let post = post_from(
+            "---\ntitle: '</script><b>Hi</b>'\ntopics: [rust]\npublished: true\npublished_at: 2024-01-02\ntldr: Summary\n---\n",
+        );
+        let author = Author {
+            name: "Me",
+            links: vec!["https://github.com/me", ""],
+        };
+        let html = post_json_ld_html(
+            &post,
+            "Blog",
+            &author,
+            Some("https://example.com"),
+            Some("card.png"),
+        );
+
+        assert!(!html.contains("</script><b>"));
+        assert_eq!(
+            parse_json_ld(&html),
+            json!({
+                "@context": "https://schema.org",
+                "@type": "BlogPosting",
+                "headline": "</script><b>Hi</b>",
+                "datePublished": "2024-01-02",
+                "author": {
+                    "@type": "Person",
+                    "name": "Me",
+                    "sameAs": ["https://github.com/me"],
+                },
+                "description": "Summary",
+                "keywords": ["rust"],
+                "image": "https://example.com/card.png",
+                "url": "https://example.com/posts/hello.html",
+                "mainEntityOfPage": "https://example.com/posts/hello.html",
+                "isPartOf": {
+                    "@type": "Blog",
+                    "name": "Blog",
+                    "url": "https://example.com/",
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_index_json_ld_html() {
+        let posts = [post_from(
+            "---\ntitle: Hello\ntopics: []\npublished: true\npublished_at: 2024-01-02\ntldr: null\n---\n",
+        )];
+        let author = Author {
+            name: "Me",
+            links: Vec::new(),
+        };
+        let html = index_json_ld_html("Blog", &author, &posts, Some("https://example.com/"));
+        let data = parse_json_ld(&html);
+
+        assert_eq!(
+            data["@graph"][0]["potentialAction"]["target"]["urlTemplate"],
+            "https://example.com/?q={search_term_string}"
+        );
+        assert_eq!(
+            data["@graph"][1]["blogPost"][0]["url"],
+            "https://example.com/posts/hello.html"
+        );
+
+        let data = parse_json_ld(&index_json_ld_html("Blog", &author, &posts, None));
+        assert!(data["@graph"][0].get("potentialAction").is_none());
+    }
+}
@@ -0,0 +1,245 @@
+use std::path::Path;
+
+use crate::diagnostic::Diagnostic;
+use crate::directive::{IncludeContext, missing_include_html, read_include};
+use crate::escape::escape_html;
+use crate::raw_html::generated;
+
+/// Whether the first row of a table file is a header row.
+#[derive(Debug, Default, PartialEq)]
+pub enum HeaderMode {
+    /// Treat the first row as a header unless it contains a numeric cell.
+    #[default]
+    Auto,
+    Yes,
+    No,
+}
+
+/// Options accepted by `@table[file.csv ...]`.
+#[derive(Debug, Default, PartialEq)]
+pub struct TableOptions {
+    pub header: HeaderMode,
+    /// Columns to render, by header name or 1-based index. Empty renders every column.
+    pub columns: Vec<String>,
+    /// Right-align columns whose body cells are all numeric.
+    pub align_numeric: bool,
+}
+
+impl TableOptions {
+    /// Parses space separated options such as `header=yes`, `columns=name,3` and `align=numeric`.
+    pub fn parse(options: &[&str]) -> Result<Self, String> {
+        let mut parsed = TableOptions::default();
+        for option in options {
+            let Some((key, value)) = option.split_once('=') else {
+                return Err(format!("unknown option `{option}`"));
+            };
+            match (key, value) {
+                ("header", "auto") => parsed.header = HeaderMode::Auto,
+                ("header", "yes") => parsed.header = HeaderMode::Yes,
+                ("header", "no") => parsed.header = HeaderMode::No,
+                ("columns", columns) => {
+                    parsed.columns = columns.split(',').map(|c| c.trim().to_string()).collect()
+                }
+                ("align", "numeric") => parsed.align_numeric = true,
+                _ => return Err(format!("unknown option `{option}`")),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+fn is_numeric(cell: &str) -> bool {
+    let cell = cell.trim().trim_end_matches('%');
+    !cell.is_empty() && cell.parse::<f64>().is_ok()
+}
+
+/// Renders delimited data as an HTML table.
+///
+/// Returns an error for malformed data or a column selection that does not match the file.
+pub fn render_table(data: &str, delimiter: u8, options: &TableOptions) -> Result<String, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(data.as_bytes());
+
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        rows.push(record.iter().map(str::to_string).collect());
+    }
+
+    let has_header = match options.header {
+        HeaderMode::Yes => true,
+        HeaderMode::No => false,
+        HeaderMode::Auto => rows
+            .first()
+            .is_some_and(|row| !row.iter().any(|cell| is_numeric(cell))),
+    };
+    let header = if has_header && !rows.is_empty() {
+        Some(rows.remove(0))
+    } else {
+        None
+    };
+
+    let width = header
+        .iter()
+        .chain(rows.iter())
+        .map(Vec::len)
+        .max()
+        .unwrap_or(0);
+    let columns: Vec<usize> = if options.columns.is_empty() {
+        (0..width).collect()
+    } else {
+        options
+            .columns
+            .iter()
+            .map(|column| {
+                header
+                    .as_ref()
+                    .and_then(|header| header.iter().position(|name| name.trim() == column))
+                    .or_else(|| {
+                        column
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|i| (1..=width).contains(i))
+                            .map(|i| i - 1)
+                    })
+                    .ok_or_else(|| format!("unknown column `{column}`"))
+            })
+            .collect::<Result<_, _>>()?
+    };
+
+    let cell = |row: &[String], i: usize| row.get(i).map_or("", String::as_str).to_string();
+    let numeric: Vec<bool> = columns
+        .iter()
+        .map(|&i| {
+            options.align_numeric
+                && rows.iter().any(|row| !cell(row, i).trim().is_empty())
+                && rows.iter().all(|row| {
+                    let value = cell(row, i);
+                    value.trim().is_empty() || is_numeric(&value)
+                })
+        })
+        .collect();
+    let class = |numeric: bool| if numeric { r#" class="numeric""# } else { "" };
+
+    let mut html = String::from("<table class=\"data-table\">\n");
+    if let Some(header) = &header {
+        html.push_str("<thead>\n<tr>");
+        for (&i, &numeric) in columns.iter().zip(&numeric) {
+            let name = escape_html(&cell(header, i));
+            let class = class(numeric);
+            html.push_str(&format!("<th{class}>{name}</th>"));
+        }
+        html.push_str("</tr>\n</thead>\n");
+    }
+    html.push_str("<tbody>\n");
+    for row in &rows {
+        html.push_str("<tr>");
+        for (&i, &numeric) in columns.iter().zip(&numeric) {
+            let value = escape_html(&cell(row, i));
+            let class = class(numeric);
+            html.push_str(&format!("<td{class}>{value}</td>"));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n");
+
+    Ok(generated(&html))
+}
+
+/// Expands a single `@table[file.csv options...]` directive into an HTML table.
+///
+/// `.tsv` and `.tab` files are read as tab separated, everything else as comma separated.
+/// See [`TableOptions::parse`] for the supported options.
+pub(crate) fn expand_table_include(
+    arg: &str,
+    line: usize,
+    ctx: &IncludeContext,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> String {
+    let mut parts = arg.split_whitespace();
+    let file_path = parts.next().unwrap_or_default();
+    let options: Vec<&str> = parts.collect();
+
+    let options = match TableOptions::parse(&options) {
+        Ok(options) => options,
+        Err(e) => {
+            diagnostics.push(Diagnostic::warning(
+                ctx.path,
+                line,
+                format!("`@table[{arg}]`: {e}"),
+            ));
+            TableOptions::default()
+        }
+    };
+
+    let Some((_, data)) = read_include("table", file_path, line, ctx, diagnostics) else {
+        return missing_include_html("table", file_path);
+    };
+
+    let delimiter = match Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+    {
+        Some("tsv" | "tab") => b'\t',
+        _ => b',',
+    };
+
+    match render_table(&data, delimiter, &options) {
+        Ok(html) => html,
+        Err(e) => {
+            diagnostics.push(Diagnostic::warning(
+                ctx.path,
+                line,
+                format!("`@table` could not render {file_path}: {e}"),
+            ));
+            missing_include_html("table", file_path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_html::without_markers;
+
+    const CSV: &str = "name,time (ms),note\nparse,12.5,fast\nrender,130,\"slow, <b>\"\n";
+
+    #[test]
+    fn test_render_table_detects_header_and_escapes_cells() {
+        let html = render_table(CSV, b',', &TableOptions::default()).unwrap();
+        let html = without_markers(&html);
+
+        assert!(html.contains("<thead>\n<tr><th>name</th><th>time (ms)</th><th>note</th></tr>"));
+        assert!(html.contains("<td>parse</td><td>12.5</td><td>fast</td>"));
+        assert!(html.contains("<td>slow, &lt;b&gt;</td>"));
+    }
+
+    #[test]
+    fn test_render_table_without_header() {
+        let html = render_table("1\t2\n3\t4\n", b'\t', &TableOptions::default()).unwrap();
+        let html = without_markers(&html);
+
+        assert!(!html.contains("<thead>"));
+        assert!(html.contains("<tr><td>1</td><td>2</td></tr>"));
+    }
+
+    #[test]
+    fn test_render_table_selects_and_aligns_columns() {
+        let options = TableOptions::parse(&["columns=2,name", "align=numeric"]).unwrap();
+        let html = render_table(CSV, b',', &options).unwrap();
+        let html = without_markers(&html);
+
+        assert!(html.contains(r#"<tr><th class="numeric">time (ms)</th><th>name</th></tr>"#));
+        assert!(html.contains(r#"<tr><td class="numeric">12.5</td><td>parse</td></tr>"#));
+        assert!(!html.contains("fast"));
+    }
+
+    #[test]
+    fn test_render_table_unknown_column() {
+        let options = TableOptions::parse(&["columns=missing"]).unwrap();
+        assert!(render_table(CSV, b',', &options).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+use std::path::Path;
+
+use crate::diagnostic::Diagnostic;
+use crate::directive::{IncludeContext, preprocess_directives};
+use crate::metadata::{BlogMeta, Post};
+use crate::options::RenderOptions;
+
+/// A post named `hello` with the given front matter and an empty body.
+pub(crate) fn post_from(front_matter: &str) -> Post {
+    let (meta, _) = BlogMeta::from_markdown_str(front_matter).unwrap();
+    Post {
+        meta,
+        html_body: String::new(),
+        markdown_body: String::new(),
+        name: "hello".to_string(),
+        diagnostics: Vec::new(),
+        links: Vec::new(),
+    }
+}
+
+/// Runs `preprocess` on `markdown` as the content of `post.md` in `dir`, and returns
+/// the result with the diagnostics it reported.
+pub(crate) fn preprocess_with(
+    preprocess: fn(&str, &IncludeContext, &mut Vec<Diagnostic>) -> String,
+    markdown: &str,
+    dir: &Path,
+    options: &RenderOptions,
+) -> (String, Vec<Diagnostic>) {
+    let post = dir.join("post.md");
+    let ctx = IncludeContext {
+        path: &post,
+        line_offset: 0,
+        options,
+        parents: &[],
+    };
+    let mut diagnostics = Vec::new();
+    let result = preprocess(markdown, &ctx, &mut diagnostics);
+    (result, diagnostics)
+}
+
+/// Expands every directive in `markdown` as the content of `post.md` in `dir`.
+pub(crate) fn preprocess(
+    markdown: &str,
+    dir: &Path,
+    options: &RenderOptions,
+) -> (String, Vec<Diagnostic>) {
+    preprocess_with(preprocess_directives, markdown, dir, options)
+}
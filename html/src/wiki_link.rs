@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+
+use crate::escape::escape_html;
+use crate::metadata::Post;
+use crate::prose::map_prose;
+use crate::raw_html::generated;
+
+/// Resolves `[[post-name]]` and `[[post-name|label]]` links against `titles`, which maps
+/// the names of published posts to their titles.
+///
+/// A resolved link becomes `<a href="post-name.html" class="wiki-link">`, labelled with
+/// the target's title unless a label is given. Links inside code are left alone, as are
+/// links to unknown posts. Returns the markdown, the names of the linked posts and the
+/// targets that did not match any post.
+pub fn resolve_wiki_links(
+    markdown: &str,
+    titles: &BTreeMap<String, String>,
+) -> (String, Vec<String>, Vec<String>) {
+    let mut links: Vec<String> = Vec::new();
+    let mut unresolved = Vec::new();
+
+    let output = map_prose(markdown, |text| {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(pos) = rest.find("[[") {
+            output.push_str(&rest[..pos]);
+            rest = &rest[pos..];
+
+            let link = rest[2..]
+                .find("]]")
+                .map(|end| &rest[2..end + 2])
+                .filter(|inner| !inner.trim().is_empty() && !inner.contains('['));
+            let Some(inner) = link else {
+                output.push('[');
+                rest = &rest[1..];
+                continue;
+            };
+
+            let (target, label) = match inner.split_once('|') {
+                Some((target, label)) => (target.trim(), Some(label.trim())),
+                None => (inner.trim(), None),
+            };
+            match titles.get(target) {
+                Some(title) => {
+                    let label = escape_html(label.unwrap_or(title));
+                    let href = escape_html(target);
+                    output.push_str(&generated(&format!(
+                        "<a href=\"{href}.html\" class=\"wiki-link\">{label}</a>"
+                    )));
+                    if !links.iter().any(|link| link == target) {
+                        links.push(target.to_string());
+                    }
+                }
+                None => {
+                    output.push_str(&rest[..inner.len() + 4]);
+                    unresolved.push(target.to_string());
+                }
+            }
+            rest = &rest[inner.len() + 4..];
+        }
+        output.push_str(rest);
+        output
+    });
+
+    (output, links, unresolved)
+}
+
+/// Renders the "Linked from" section listing the posts that link to a post.
+///
+/// Returns an empty string when no post links to it.
+pub fn backlinks_html(posts: &[&Post]) -> String {
+    if posts.is_empty() {
+        return String::new();
+    }
+
+    let items: Vec<String> = posts
+        .iter()
+        .map(|post| {
+            format!(
+                r#"<li><a href="{name}.html">{title}</a></li>"#,
+                name = escape_html(&post.name),
+                title = escape_html(&post.meta.title)
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<section class="backlinks">
+        <h2>Linked from</h2>
+        <ul>
+          {items}
+        </ul>
+      </section>"#,
+        items = items.join("\n          ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_html::without_markers;
+
+    fn titles() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("rust-notes".to_string(), "Rust <Notes>".to_string()),
+            ("wasm".to_string(), "WebAssembly".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_resolve_wiki_links() {
+        let markdown = "See [[rust-notes]] and [[wasm|the wasm post]], again [[wasm]].";
+        let (result, links, unresolved) = resolve_wiki_links(markdown, &titles());
+        let result = without_markers(&result);
+
+        assert_eq!(
+            result,
+            "See <a href=\"rust-notes.html\" class=\"wiki-link\">Rust &lt;Notes&gt;</a> and <a href=\"wasm.html\" class=\"wiki-link\">the wasm post</a>, again <a href=\"wasm.html\" class=\"wiki-link\">WebAssembly</a>."
+        );
+        assert_eq!(links, vec!["rust-notes".to_string(), "wasm".to_string()]);
+        assert!(unresolved.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_wiki_links_skips_code_and_reports_unknown_targets() {
+        let markdown = "`[[wasm]]` and ``a ` [[wasm]]``\n\n```rust\nlet x = a[[0]];\n```\n\n    [[wasm]] [[nope]]\n\n[[missing|label]] [x]";
+        let (result, links, unresolved) = resolve_wiki_links(markdown, &titles());
+
+        assert_eq!(result, markdown);
+        assert!(links.is_empty());
+        assert_eq!(unresolved, vec!["missing".to_string()]);
+    }
+}
@@ -1,13 +1,19 @@
 use crate::posts_json::generate_posts_json;
-use config::MejiroConfig;
+use config::{AssetMode, HighlightEngine, MejiroConfig};
 use html;
-use html::metadata::Post;
+use html::metadata::{BlogMeta, BlogParseError, Post};
+use html::options::{Highlighting, RenderOptions, RunOptions};
 use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use walkdir::WalkDir;
 
-pub fn compile(input_dir: &str, output_dir: &str, config_path: &str) {
+/// Builds the site. A post with build errors is left out and makes the process exit
+/// with a non-zero status once the rest of the site is written. With `strict`, build
+/// warnings are treated as errors, and so is a post that cannot be parsed.
+pub fn compile(input_dir: &str, output_dir: &str, config_path: &str, strict: bool) {
     fs::create_dir_all(output_dir).unwrap();
 
     let config = MejiroConfig::load_config(config_path);
@@ -35,30 +41,55 @@ pub fn compile(input_dir: &str, output_dir: &str, config_path: &str) {
     // Write mejiro-search-pkg
     write_search_pkg(output_dir);
 
+    // Write the stylesheet for code highlighted at compile time
+    let highlight_css = write_highlight_css(output_dir, &config);
+
     // Build footer
     let footer = html::footer_html(&config.site_title);
 
     // Collect published posts
+    let post_files = post_files(input_dir);
+    let mut render_options = render_options(input_dir, config_path, &config, strict);
+    if highlight_css.is_some() {
+        render_options.highlighting = Highlighting::Static;
+    }
+    render_options.post_titles = post_titles(&post_files);
     let mut posts = Vec::new();
-    for entry in WalkDir::new(input_dir)
-        .into_iter()
-        .filter_map(Result::ok)
-        .filter(|e| e.path().extension().map(|ext| ext == "md").unwrap_or(false))
-    {
-        match Post::from_markdown_file(entry.path()) {
-            Ok(Some(post)) => posts.push(post),
+    let mut has_errors = false;
+    let mut has_parse_errors = false;
+    for path in &post_files {
+        match Post::from_markdown_file(path, &render_options) {
+            Ok(Some(post)) => {
+                for diagnostic in &post.diagnostics {
+                    eprintln!("{diagnostic}");
+                }
+                posts.push(post);
+            }
             Ok(None) => {
-                let path = entry.path().display();
+                let path = path.display();
                 println!("Skipping unpublished post: {path}");
             }
+            Err(BlogParseError::Diagnostics(diagnostics)) => {
+                for diagnostic in &diagnostics {
+                    eprintln!("{diagnostic}");
+                }
+                has_errors = true;
+            }
             Err(e) => {
-                let path = entry.path().display();
+                let path = path.display();
                 eprintln!("Error parsing {path}: {e}");
+                has_parse_errors = true;
             }
         }
     }
     posts.sort_by(|a, b| b.meta.published_at.cmp(&a.meta.published_at));
 
+    // Generate social cards for posts
+    let has_cards = match &config.social.card_font {
+        Some(font_path) => write_og_cards(&mut posts, Path::new(output_dir), &config, font_path),
+        None => false,
+    };
+
     // Build post pages
     let icon_path_rel = format!("../{icon_file_name}");
     let aside = html::aside_html(
@@ -66,15 +97,20 @@ pub fn compile(input_dir: &str, output_dir: &str, config_path: &str) {
         &config.owner.github_link,
         &config.owner.linkedin_link,
         &icon_path_rel,
+        "../index.html",
+    );
+    let head = format!(
+        "{icon}\n  {highlight}",
+        icon = html::icon_html(&icon_path_rel),
+        highlight = highlight_head_html(highlight_css.as_ref(), &config, "../")
     );
-    let icon = html::icon_html(&icon_path_rel);
     build_post_pages(
         &posts,
         Path::new(output_dir),
-        &config.site_title,
+        &config,
         &aside,
         &footer,
-        &icon,
+        &head,
         &css_filename,
     );
 
@@ -86,11 +122,20 @@ pub fn compile(input_dir: &str, output_dir: &str, config_path: &str) {
         &css_filename,
         icon_file_name,
         &footer,
+        &highlight_head_html(highlight_css.as_ref(), &config, ""),
     );
 
     // Generate posts.json
     generate_posts_json(&posts, output_dir);
 
+    if config.security.sri || config.security.csp {
+        secure_pages(output_dir, &config);
+    }
+
+    if config.assets == AssetMode::Offline {
+        report_remote_assets(output_dir);
+    }
+
     let post_paths: Vec<String> = posts
         .iter()
         .map(|post| format!("{name}.html", name = post.name))
@@ -102,6 +147,9 @@ pub fn compile(input_dir: &str, output_dir: &str, config_path: &str) {
     println!("├── icon.png");
     println!("├── images/");
     println!("├── mejiro-search-pkg/");
+    if has_cards {
+        println!("├── og/");
+    }
     println!("├── index.html");
     println!("├── posts/");
 
@@ -121,24 +169,245 @@ pub fn compile(input_dir: &str, output_dir: &str, config_path: &str) {
         }
     }
 
+    if has_errors || (strict && has_parse_errors) {
+        eprintln!("❌ Build failed: some posts could not be built.");
+        std::process::exit(1);
+    }
+
     println!("✅ Build complete. Output directory: ./public");
 }
 
+/// Returns the markdown files under `input_dir` that are posts (not partials).
+fn post_files(input_dir: &str) -> Vec<PathBuf> {
+    WalkDir::new(input_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.path().extension().map(|ext| ext == "md").unwrap_or(false))
+        .filter(|e| {
+            let partial = is_partial(e.path());
+            if partial {
+                let path = e.path().display();
+                println!("Skipping partial: {path}");
+            }
+            !partial
+        })
+        .map(|e| e.path().to_path_buf())
+        .collect()
+}
+
+/// Reads the front matter of every post so `[[post-name]]` links can be resolved
+/// before the posts themselves are built. Unpublished posts are left out.
+fn post_titles(paths: &[PathBuf]) -> BTreeMap<String, String> {
+    paths
+        .iter()
+        .filter_map(|path| {
+            let content = fs::read_to_string(path).ok()?;
+            let (meta, _) = BlogMeta::from_markdown_str(&content).ok()?;
+            let name = path.file_stem()?.to_string_lossy().to_string();
+            meta.published.then_some((name, meta.title))
+        })
+        .collect()
+}
+
+/// Markdown files whose name starts with `_` are partials for `@include[...]`, not posts.
+fn is_partial(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('_'))
+}
+
+fn render_options(
+    input_dir: &str,
+    config_path: &str,
+    config: &MejiroConfig,
+    strict: bool,
+) -> RenderOptions {
+    // Relative roots are relative to mejiro.yml, like the run cache
+    let mut include_roots = vec![Path::new(input_dir).to_path_buf()];
+    include_roots.extend(
+        config
+            .include_roots
+            .iter()
+            .map(|root| config_dir(config_path).join(root)),
+    );
+
+    for root in &include_roots {
+        if !root.exists() {
+            eprintln!("Include root not found: {root:?}");
+        }
+    }
+
+    // Cache @run output next to mejiro.yml so rebuilds skip unchanged scripts
+    let cache_dir = cache_dir(config_path);
+    let run = config.run.as_ref().map(|run| RunOptions {
+        interpreters: run.interpreters.clone(),
+        timeout: Duration::from_secs(run.timeout_secs),
+        cache_dir: Some(cache_dir),
+    });
+
+    RenderOptions {
+        include_roots,
+        strict,
+        run,
+        footnotes: config.markdown.footnotes,
+        bibliography: config.bibliography.as_ref().map(PathBuf::from),
+        citations: config.markdown.citations,
+        raw_html: config.markdown.raw_html,
+        ..Default::default()
+    }
+}
+
+/// First 8 hex digits of the SHA-256 of `bytes`, used to give assets cache-busting names.
+fn short_hash(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    let hash_hex = format!("{:x}", hasher.finalize());
+    hash_hex[..8].to_string()
+}
+
 fn css_filename_with_hash(css_path: &Path) -> Option<String> {
     if css_path.exists() {
         let bytes = fs::read(css_path).ok()?;
-        let mut hasher = Sha256::new();
-        hasher.update(&bytes);
-        let hash = hasher.finalize();
-        let hash_hex = format!("{hash:x}");
-
-        Some(format!("style.{hash}.css", hash = &hash_hex[..8]))
+        Some(format!("style.{hash}.css", hash = short_hash(&bytes)))
     } else {
         eprintln!("CSS file not found: {css_path:?}");
         None
     }
 }
 
+/// Content-hashed file names of the compile-time highlighting stylesheets.
+struct HighlightStylesheets {
+    light: String,
+    dark: String,
+}
+
+/// With the `static` highlight engine, or with offline assets (Prism is only available
+/// from a CDN, so the `prism` engine is overridden with a warning), writes the
+/// stylesheets of the light and dark themes for highlighted code.
+fn write_highlight_css(output_dir: &str, config: &MejiroConfig) -> Option<HighlightStylesheets> {
+    let highlight = &config.styles.highlight;
+    if config.assets == AssetMode::Offline && highlight.engine == HighlightEngine::Prism {
+        eprintln!(
+            "warning: `assets: offline` highlights code at compile time, so \
+             `styles.highlight.engine: prism` is ignored; set it to `static` to silence this warning"
+        );
+    }
+    if highlight.engine != HighlightEngine::Static && config.assets != AssetMode::Offline {
+        return None;
+    }
+
+    let write = |theme: &str, stem: &str| {
+        let css = html::highlight::highlight_css(theme).unwrap_or_else(|e| {
+            eprintln!("❌ {e}");
+            std::process::exit(1);
+        });
+        let filename = format!("{stem}.{hash}.css", hash = short_hash(css.as_bytes()));
+        fs::write(Path::new(output_dir).join(&filename), css).unwrap();
+        filename
+    };
+    Some(HighlightStylesheets {
+        light: write(&highlight.theme, "highlight"),
+        dark: write(&highlight.dark_theme, "highlight-dark"),
+    })
+}
+
+/// Returns the `<head>` markup for code highlighting and the colour scheme: the
+/// compile-time stylesheets if there are any, otherwise Prism, followed by the script
+/// that applies the reader's colour scheme. `prefix` is the path from the page to the
+/// output root.
+fn highlight_head_html(
+    highlight_css: Option<&HighlightStylesheets>,
+    config: &MejiroConfig,
+    prefix: &str,
+) -> String {
+    let highlight = match highlight_css {
+        Some(css) => html::color_scheme::color_scheme_stylesheets_html(
+            &format!("{prefix}{}", css.light),
+            &format!("{prefix}{}", css.dark),
+        ),
+        None => {
+            let themes = &config.styles.highlight;
+            html::highlight::prism_head_html(&themes.prism_theme, &themes.prism_dark_theme)
+                .unwrap_or_else(|e| {
+                    eprintln!("❌ {e}");
+                    std::process::exit(1);
+                })
+        }
+    };
+    format!(
+        "{highlight}\n  <script>{script}</script>",
+        script = html::color_scheme::COLOR_SCHEME_SCRIPT
+    )
+}
+
+/// Directory next to mejiro.yml where build results (e.g. `@run` output) are cached.
+fn cache_dir(config_path: &str) -> PathBuf {
+    config_dir(config_path).join(".mejiro-cache")
+}
+
+/// Directory containing mejiro.yml.
+fn config_dir(config_path: &str) -> &Path {
+    Path::new(config_path)
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+}
+
+/// Adds the pinned integrity hashes of mejiro's CDN assets to every page, and a
+/// Content Security Policy computed from the final page when enabled. Assets left
+/// without a hash are reported.
+fn secure_pages(output_dir: &str, config: &MejiroConfig) {
+    let integrities = if config.security.sri {
+        html::highlight::prism_integrities()
+    } else {
+        BTreeMap::new()
+    };
+    let mut unpinned = BTreeSet::new();
+
+    for entry in WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "html"))
+    {
+        let Ok(page) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        if config.security.sri {
+            unpinned.extend(
+                html::security::external_assets(&page)
+                    .into_iter()
+                    .filter(|url| !integrities.contains_key(url)),
+            );
+        }
+        let page = html::security::secure_page(&page, &integrities, config.security.csp);
+        fs::write(entry.path(), page).unwrap();
+    }
+    for url in unpinned {
+        eprintln!("warning: no pinned integrity hash for {url}, so it is loaded without one");
+    }
+}
+
+/// Warns about pages that still load something from the network in offline mode,
+/// e.g. an image embedded by URL in a post.
+fn report_remote_assets(output_dir: &str) {
+    for entry in WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|e| e.path().extension().is_some_and(|ext| ext == "html"))
+    {
+        let Ok(html) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        for pattern in [r#" src="http"#, r#"rel="stylesheet" href="http"#] {
+            for (pos, _) in html.match_indices(pattern) {
+                let url_start = pos + pattern.len() - "http".len();
+                let url = html[url_start..].split('"').next().unwrap_or_default();
+                let page = entry.path().display();
+                eprintln!("warning: {page} loads {url}, which is not available offline");
+            }
+        }
+    }
+}
+
 fn copy_file(src: &Path, dest: &Path, description: &str) -> std::io::Result<()> {
     if src.exists() {
         fs::copy(src, dest)?;
@@ -165,13 +434,69 @@ fn copy_images(src_dir: &Path, dest_dir: &Path) {
     }
 }
 
+/// Renders a social card image for each post to `og/<name>.png`, and uses it as the
+/// `og:image` of posts without their own image. Returns whether cards were written.
+fn write_og_cards(
+    posts: &mut [Post],
+    output_dir: &Path,
+    config: &MejiroConfig,
+    font_path: &str,
+) -> bool {
+    let font = match fs::read(font_path)
+        .map_err(|e| e.to_string())
+        .and_then(html::og_card::CardFont::from_bytes)
+    {
+        Ok(font) => font,
+        Err(e) => {
+            eprintln!(
+                "warning: could not load the card font {font_path}, so no social cards are generated: {e}"
+            );
+            return false;
+        }
+    };
+    let icon = fs::read(&config.styles.icon).ok();
+
+    let og_dir = output_dir.join("og");
+    fs::create_dir_all(&og_dir).unwrap();
+    for post in posts {
+        let card = html::og_card::OgCard {
+            title: &post.meta.title,
+            site_title: &config.site_title,
+            published_at: &post.meta.published_at,
+            topics: &post.meta.topics,
+            icon_png: icon.as_deref(),
+        };
+        match html::og_card::render_og_card(&card, &font) {
+            Ok(png) => {
+                fs::write(og_dir.join(format!("{name}.png", name = post.name)), png).unwrap();
+                if post.meta.image.is_none() {
+                    post.meta.image = Some(format!("../og/{name}.png", name = post.name));
+                }
+            }
+            Err(e) => {
+                let name = &post.name;
+                eprintln!("warning: could not render the social card of {name}: {e}");
+            }
+        }
+    }
+    true
+}
+
+/// The blog owner, as the author in structured data.
+fn author(config: &MejiroConfig) -> html::structured_data::Author<'_> {
+    html::structured_data::Author {
+        name: &config.owner.name,
+        links: vec![&config.owner.github_link, &config.owner.linkedin_link],
+    }
+}
+
 fn build_post_pages(
     posts: &[Post],
     output_dir: &Path,
-    site_title: &str,
+    config: &MejiroConfig,
     aside: &str,
     footer: &str,
-    icon: &str,
+    head: &str,
     css_filename: &str,
 ) {
     for post in posts {
@@ -180,8 +505,38 @@ fn build_post_pages(
             .join(format!("{name}.html", name = post.name));
         fs::create_dir_all(output_path.parent().unwrap()).unwrap();
 
+        // Posts that link to this one with [[post-name]]
+        let linked_from: Vec<&Post> = posts
+            .iter()
+            .filter(|other| other.name != post.name && other.links.contains(&post.name))
+            .collect();
+        let backlinks = html::backlinks_html(&linked_from);
+        let head = format!(
+            "{head}\n  {social}\n  {json_ld}",
+            social = html::post_social_html(
+                post,
+                config.site_url.as_deref(),
+                config.social.image.as_deref()
+            ),
+            json_ld = html::structured_data::post_json_ld_html(
+                post,
+                &config.site_title,
+                &author(config),
+                config.site_url.as_deref(),
+                config.social.image.as_deref()
+            )
+        );
+
         let css_relative_path = format!("../{css_filename}");
-        let post_html = html::post_html(post, site_title, aside, footer, icon, &css_relative_path);
+        let post_html = html::post_html(
+            post,
+            &config.site_title,
+            aside,
+            footer,
+            &head,
+            &css_relative_path,
+            &backlinks,
+        );
         fs::write(&output_path, post_html).unwrap();
     }
 }
@@ -193,21 +548,37 @@ fn build_index_page(
     css_filename: &str,
     icon_filename: &str,
     footer: &str,
+    highlight_head: &str,
 ) {
     let aside = html::aside_html(
         &config.owner.name,
         &config.owner.github_link,
         &config.owner.linkedin_link,
         icon_filename,
+        "index.html",
+    );
+    let head = format!(
+        "{icon}\n  {highlight_head}\n  {social}\n  {json_ld}",
+        icon = html::icon_html(icon_filename),
+        social = html::index_social_html(
+            &config.site_title,
+            config.site_url.as_deref(),
+            config.social.image.as_deref()
+        ),
+        json_ld = html::structured_data::index_json_ld_html(
+            &config.site_title,
+            &author(config),
+            posts,
+            config.site_url.as_deref()
+        )
     );
-    let icon = html::icon_html(icon_filename);
 
     let index_html = html::index_html(
         &config.owner.name,
         posts,
         &aside,
         footer,
-        &icon,
+        &head,
         css_filename,
     );
     let index_path = output_dir.join("index.html");
@@ -232,3 +603,162 @@ fn mejiro_search_js() -> &'static str {
 fn mejiro_search_bg_wasm() -> &'static [u8] {
     include_bytes!("../assets/mejiro-search-pkg/mejiro_search_bg.wasm")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Builds a site with a post containing a code block and a post with its own
+    /// `image`, and returns the output directory. `settings` is appended to mejiro.yml.
+    fn build_site(dir: &TempDir, settings: &str) -> PathBuf {
+        let root = dir.path();
+        let posts = root.join("posts");
+        let output = root.join("public");
+        fs::create_dir_all(&posts).unwrap();
+        fs::write(root.join("style.css"), "body {}").unwrap();
+        fs::write(
+            posts.join("20240102-code.md"),
+            "---\ntitle: Code\ntopics: [rust]\npublished: true\npublished_at: 2024-01-02\ntldr: null\n---\n\n```rust\nfn main() {}\n```\n",
+        )
+        .unwrap();
+        fs::write(
+            posts.join("20240103-cover.md"),
+            "---\ntitle: Cover\ntopics: []\npublished: true\npublished_at: 2024-01-03\ntldr: null\nimage: https://example.com/cover.png\n---\n\nText\n",
+        )
+        .unwrap();
+        let config_path = root.join("mejiro.yml");
+        fs::write(
+            &config_path,
+            format!(
+                "owner:\n  name: Owner\n  github_link: https://github.com/owner\n  linkedin_link: https://linkedin.com/in/owner\nsite_title: Blog\nstyles:\n  css_file: {css}\n  icon: {icon}\nimages_dir: {images}\n{settings}",
+                css = root.join("style.css").display(),
+                icon = root.join("icon.png").display(),
+                images = root.join("images").display(),
+            ),
+        )
+        .unwrap();
+
+        compile(
+            posts.to_str().unwrap(),
+            output.to_str().unwrap(),
+            config_path.to_str().unwrap(),
+            false,
+        );
+        output
+    }
+
+    fn html_pages(output: &Path) -> Vec<(PathBuf, String)> {
+        WalkDir::new(output)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "html"))
+            .map(|e| {
+                (
+                    e.path().to_path_buf(),
+                    fs::read_to_string(e.path()).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_include_roots_are_relative_to_config() {
+        let config: MejiroConfig = serde_yaml::from_str(
+            "owner:\n  name: Owner\n  github_link: g\n  linkedin_link: l\nsite_title: Blog\nstyles:\n  css_file: style.css\n  icon: icon.png\ninclude_roots: [snippets, /abs]\n",
+        )
+        .unwrap();
+
+        let options = render_options("posts", "site/mejiro.yml", &config, false);
+        assert_eq!(
+            options.include_roots,
+            [
+                PathBuf::from("posts"),
+                PathBuf::from("site/snippets"),
+                PathBuf::from("/abs")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_offline_output_has_no_cdn_references() {
+        let dir = TempDir::new().unwrap();
+        let output = build_site(&dir, "assets: offline\n");
+
+        let pages = html_pages(&output);
+        assert!(pages.len() >= 2);
+        for (path, page) in &pages {
+            assert!(
+                !page.contains("cdn.jsdelivr.net"),
+                "{} loads from the CDN",
+                path.display()
+            );
+        }
+        let post = fs::read_to_string(output.join("posts/20240102-code.html")).unwrap();
+        assert!(post.contains("class=\"hl-code\""));
+    }
+
+    #[test]
+    fn test_cdn_output_loads_prism() {
+        let dir = TempDir::new().unwrap();
+        let output = build_site(&dir, "");
+
+        let post = fs::read_to_string(output.join("posts/20240102-code.html")).unwrap();
+        assert!(post.contains("cdn.jsdelivr.net/npm/prismjs"));
+        assert!(!output.read_dir().unwrap().any(|entry| {
+            let name = entry.unwrap().file_name();
+            name.to_string_lossy().starts_with("highlight")
+        }));
+    }
+
+    #[test]
+    fn test_highlight_css_is_content_hashed() {
+        let dir = TempDir::new().unwrap();
+        let output = build_site(&dir, "assets: offline\n");
+
+        let post = fs::read_to_string(output.join("posts/20240102-code.html")).unwrap();
+        for (stem, theme) in [
+            ("highlight", "InspiredGitHub"),
+            ("highlight-dark", "base16-ocean.dark"),
+        ] {
+            let css = html::highlight::highlight_css(theme).unwrap();
+            let filename = format!("{stem}.{}.css", short_hash(css.as_bytes()));
+            assert_eq!(fs::read_to_string(output.join(&filename)).unwrap(), css);
+            assert!(
+                post.contains(&format!("href=\"../{filename}\"")),
+                "{filename}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_og_cards() {
+        let dir = TempDir::new().unwrap();
+        let font = Path::new(env!("CARGO_MANIFEST_DIR")).join("../html/fixtures/box.ttf");
+        let output = build_site(
+            &dir,
+            &format!(
+                "site_url: https://example.com\nsocial:\n  card_font: {}\n",
+                font.display()
+            ),
+        );
+
+        for name in ["20240102-code", "20240103-cover"] {
+            let png = fs::read(output.join(format!("og/{name}.png"))).unwrap();
+            // The PNG signature, then the width and height from the IHDR chunk
+            assert_eq!(&png[1..4], b"PNG");
+            assert_eq!(png[16..20], 1200u32.to_be_bytes());
+            assert_eq!(png[20..24], 630u32.to_be_bytes());
+        }
+        let code = fs::read_to_string(output.join("posts/20240102-code.html")).unwrap();
+        assert!(code.contains(
+            r#"<meta property="og:image" content="https://example.com/og/20240102-code.png" />"#
+        ));
+        let cover = fs::read_to_string(output.join("posts/20240103-cover.html")).unwrap();
+        assert!(
+            cover.contains(
+                r#"<meta property="og:image" content="https://example.com/cover.png" />"#
+            )
+        );
+    }
+}
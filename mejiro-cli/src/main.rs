@@ -33,6 +33,9 @@ enum Commands {
         output: String,
         #[arg(short, long, default_value = "./mejiro.yml")]
         config_file: String,
+        /// Treat build warnings as errors and exit with a failure status
+        #[arg(long, default_value_t = false)]
+        strict: bool,
     },
     /// List post metadata
     List {
@@ -81,8 +84,9 @@ fn main() {
             input,
             output,
             config_file,
+            strict,
         } => {
-            compile(&input, &output, &config_file);
+            compile(&input, &output, &config_file, strict);
         }
         Commands::List { input, all } => {
             post_list(&input, all);
@@ -33,6 +33,11 @@ pub fn new(output_dir: &str) {
         published: false,
         published_at: today,
         tldr: Some("A short summary here.".to_string()),
+        footnotes: None,
+        bibliography: None,
+        citations: None,
+        image: None,
+        canonical_url: None,
     };
 
     let yaml_frontmatter = serde_yaml::to_string(&meta).expect("Failed to serialize frontmatter");
@@ -1,4 +1,5 @@
 use html::metadata::Post;
+use html::ruby::strip_ruby;
 use mejiro_search::base_search::{SearchPostData, SearchPostMetaCompile};
 use serde_json;
 use std::fs;
@@ -21,7 +22,8 @@ pub fn generate_posts_json(posts: &[Post], output_dir: &str) {
             post.meta.title.clone(),
             post.meta.topics.join(", "),
             post.meta.tldr.clone().unwrap_or_default(),
-            post.markdown_body.clone(),
+            // Index the base text of ruby annotations, not their readings
+            strip_ruby(&post.markdown_body),
         ];
         let post_data = SearchPostData {
             id: post.name.clone(),